@@ -1,172 +1,165 @@
 extern crate test;
 
-use std::collections::HashSet;
+use std::collections::HashMap;
 
 const INPUT: &[u8] = include_bytes!("../inputs/day03.txt");
 
-const POWERS_OF_TEN: [usize; 6] = [1, 10, 100, 1000, 10000, 100000];
-
 fn usize_from_bytes(bytes: &[u8]) -> usize {
-    bytes.iter().rev().enumerate().fold(0, |acc, (ix, x)| {
-        acc + (x & 0x0f) as usize * POWERS_OF_TEN[ix]
-    })
+    bytes
+        .iter()
+        .try_fold(0_usize, |acc, x| {
+            acc.checked_mul(10)?.checked_add((x & 0x0f) as usize)
+        })
+        .expect("number does not fit in usize")
 }
 
-fn part1(input: &[u8]) -> usize {
-    let symbols: HashSet<(isize, isize)> = input
-        .split(|c| *c == b'\n')
-        .enumerate()
-        .flat_map(move |(row, line)| {
-            line.iter()
-                .enumerate()
-                .flat_map(move |(col, elem)| match elem {
-                    b'0'..=b'9' => None,
-                    b'.' => None,
-                    _ => Some((row as isize, col as isize)),
-                })
-        })
-        .collect();
-
-    let numbers: Vec<(isize, isize, isize, usize)> = input
-        .split(|c| *c == b'\n')
-        .enumerate()
-        .flat_map(|(r, line)| {
-            let line = Vec::from(line);
-            let mut result = vec![];
-            let mut i: usize = 0;
-            loop {
-                if i >= line.len() {
-                    break;
-                }
-                if !line[i].is_ascii_digit() {
-                    i += 1;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Symbol {
+    row: usize,
+    col: usize,
+    kind: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Number {
+    row: usize,
+    col: usize,
+    len: usize,
+    value: usize,
+    symbols: Vec<usize>,
+}
+
+struct Schematic {
+    numbers: Vec<Number>,
+    symbols: Vec<Symbol>,
+    adjacent_numbers: Vec<Vec<usize>>,
+}
+
+impl Schematic {
+    fn parse(input: &[u8]) -> Self {
+        let lines: Vec<&[u8]> = input.split(|c| *c == b'\n').collect();
+
+        let mut symbols: Vec<Symbol> = vec![];
+        let symbol_grid: Vec<Vec<Option<usize>>> = lines
+            .iter()
+            .enumerate()
+            .map(|(row, line)| {
+                line.iter()
+                    .enumerate()
+                    .map(|(col, c)| match c {
+                        b'0'..=b'9' | b'.' => None,
+                        _ => {
+                            symbols.push(Symbol { row, col, kind: *c });
+                            Some(symbols.len() - 1)
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut numbers: Vec<Number> = vec![];
+        let mut adjacent_numbers: Vec<Vec<usize>> = vec![vec![]; symbols.len()];
+
+        for (row, line) in lines.iter().enumerate() {
+            let mut col = 0;
+            while col < line.len() {
+                if !line[col].is_ascii_digit() {
+                    col += 1;
                     continue;
                 }
-                let number_bytes = &line[i..]
+                let len = line[col..]
                     .iter()
                     .take_while(|c| c.is_ascii_digit())
-                    .cloned()
-                    .collect::<Vec<u8>>();
-                let length = number_bytes.len();
-                result.push((
-                    r as isize,
-                    i as isize,
-                    length as isize,
-                    usize_from_bytes(number_bytes),
-                ));
-                i += length;
-            }
-            result
-        })
-        .collect();
+                    .count();
 
-    numbers
-        .iter()
-        .filter_map(|(row, col_, length, number)| {
-            for col in *col_..=*col_ + *length - 1 {
-                for indices in [
-                    (*row - 1, col - 1),
-                    (*row - 1, col),
-                    (*row - 1, col + 1),
-                    (*row, col - 1),
-                    (*row, col + 1),
-                    (*row + 1, col - 1),
-                    (*row + 1, col),
-                    (*row + 1, col + 1),
-                ] {
-                    if symbols.contains(&indices) {
-                        return Some(number);
+                let mut number_symbols = vec![];
+                for r in row.saturating_sub(1)..=row + 1 {
+                    if let Some(grid_row) = symbol_grid.get(r) {
+                        for c in col.saturating_sub(1)..=col + len {
+                            if let Some(Some(symbol)) = grid_row.get(c) {
+                                number_symbols.push(*symbol);
+                                adjacent_numbers[*symbol].push(numbers.len());
+                            }
+                        }
                     }
                 }
+
+                numbers.push(Number {
+                    row,
+                    col,
+                    len,
+                    value: usize_from_bytes(&line[col..col + len]),
+                    symbols: number_symbols,
+                });
+                col += len;
             }
-            None
-        })
-        .sum()
-}
+        }
 
-const DIRECTIONS: [(usize, usize); 8] = [
-    (0, 0),
-    (0, 1),
-    (0, 2),
-    (1, 0),
-    (1, 2),
-    (2, 0),
-    (2, 1),
-    (2, 2),
-];
+        Self {
+            numbers,
+            symbols,
+            adjacent_numbers,
+        }
+    }
 
-fn part2(input: &[u8]) -> usize {
-    let width = input.iter().take_while(|c| **c != b'\n').count();
-    let dummy_line = vec![b'.'; width];
-
-    let lines: Vec<Vec<u8>> = std::iter::once(&dummy_line[..])
-        .chain(input.split(|c| *c == b'\n'))
-        .chain(std::iter::once(&dummy_line[..]))
-        .map(|line| [b".", line, b"."].concat())
-        .collect();
-
-    lines
-        .windows(3)
-        .flat_map(|window| {
-            window[1]
+    fn part_numbers(&self) -> impl Iterator<Item = &Number> {
+        self.numbers
+            .iter()
+            .filter(|number| !number.symbols.is_empty())
+    }
+
+    fn gears(&self, kind: u8, count: usize) -> impl Iterator<Item = (&Symbol, &[usize])> {
+        self.symbols
+            .iter()
+            .zip(self.adjacent_numbers.iter())
+            .filter(move |(symbol, numbers)| symbol.kind == kind && numbers.len() == count)
+            .map(|(symbol, numbers)| (symbol, &numbers[..]))
+    }
+
+    fn gear_ratios(&self, kind: u8, count: usize) -> impl Iterator<Item = usize> + '_ {
+        self.gears(kind, count).map(|(_, numbers)| {
+            numbers
                 .iter()
-                .enumerate()
-                .flat_map(|(i, v)| if *v == b'*' { Some(i) } else { None })
-                .flat_map(|i| {
-                    let mut neighbours: Vec<bool> = DIRECTIONS
-                        .into_iter()
-                        .map(|(dy, dx)| window[dy][i + dx - 1].is_ascii_digit())
-                        .collect();
-                    if neighbours[0] && neighbours[1] {
-                        neighbours[0] = false;
-                    }
-                    if neighbours[1] && neighbours[2] {
-                        neighbours[2] = false;
-                    }
-                    if neighbours[5] && neighbours[6] {
-                        neighbours[5] = false;
-                    }
-                    if neighbours[6] && neighbours[7] {
-                        neighbours[7] = false;
-                    }
-                    let real_neighbours: Vec<(usize, usize)> =
-                        std::iter::zip(neighbours, DIRECTIONS)
-                            .flat_map(|(is_neigh, dir)| if is_neigh { Some(dir) } else { None })
-                            .collect();
-                    if real_neighbours.len() != 2 {
-                        None
-                    } else {
-                        let (y1, x1) = (real_neighbours[0].0, i + real_neighbours[0].1 - 1);
-                        let (y2, x2) = (real_neighbours[1].0, i + real_neighbours[1].1 - 1);
-
-                        let left1 = window[y1][0..x1]
-                            .iter()
-                            .rev()
-                            .take_while(|c| c.is_ascii_digit())
-                            .count();
-                        let right1 = window[y1][x1..]
-                            .iter()
-                            .take_while(|c| c.is_ascii_digit())
-                            .count();
-
-                        let left2 = window[y2][0..x2]
-                            .iter()
-                            .rev()
-                            .take_while(|c| c.is_ascii_digit())
-                            .count();
-                        let right2 = window[y2][x2..]
-                            .iter()
-                            .take_while(|c| c.is_ascii_digit())
-                            .count();
-
-                        let a = usize_from_bytes(&window[y1][x1 - left1..x1 + right1]);
-                        let b = usize_from_bytes(&window[y2][x2 - left2..x2 + right2]);
-
-                        Some(a * b)
-                    }
+                .try_fold(1_usize, |acc, number| {
+                    acc.checked_mul(self.numbers[*number].value)
                 })
+                .expect("gear ratio does not fit in usize")
         })
-        .sum()
+    }
+
+    fn sums_by_kind(&self) -> HashMap<u8, usize> {
+        let mut result = HashMap::new();
+        for (symbol, numbers) in self.symbols.iter().zip(self.adjacent_numbers.iter()) {
+            let sum = result.entry(symbol.kind).or_insert(0_usize);
+            *sum = numbers
+                .iter()
+                .try_fold(*sum, |acc, number| {
+                    acc.checked_add(self.numbers[*number].value)
+                })
+                .expect("sum does not fit in usize");
+        }
+        result
+    }
+
+    fn shared_numbers(&self) -> impl Iterator<Item = &Number> {
+        self.numbers
+            .iter()
+            .filter(|number| number.symbols.len() > 1)
+    }
+}
+
+fn part1(input: &[u8]) -> usize {
+    Schematic::parse(input)
+        .part_numbers()
+        .try_fold(0_usize, |acc, number| acc.checked_add(number.value))
+        .expect("sum does not fit in usize")
+}
+
+fn part2(input: &[u8]) -> usize {
+    Schematic::parse(input)
+        .gear_ratios(b'*', 2)
+        .try_fold(0_usize, usize::checked_add)
+        .expect("sum does not fit in usize")
 }
 
 pub fn main() {
@@ -176,9 +169,31 @@ pub fn main() {
     println!("{}", part2(input));
 }
 
+pub fn explain() {
+    let input = INPUT.trim_ascii_end();
+    let schematic = Schematic::parse(input);
+
+    let mut sums: Vec<(u8, usize)> = schematic.sums_by_kind().into_iter().collect();
+    sums.sort_unstable();
+    for (kind, sum) in sums {
+        println!("{}: {}", kind as char, sum);
+    }
+
+    for number in schematic.shared_numbers() {
+        println!(
+            "{} at ({}, {}) touches {} symbols",
+            number.value,
+            number.row,
+            number.col,
+            number.symbols.len()
+        );
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::XorShift;
     use test::Bencher;
 
     const TEST_INPUT: &[u8] = include_bytes!("../test_inputs/day03.txt");
@@ -195,6 +210,154 @@ mod tests {
         assert_eq!(part2(input), 467835);
     }
 
+    #[test]
+    fn test_sums_by_kind() {
+        let input = TEST_INPUT.trim_ascii_end();
+        let sums = Schematic::parse(input).sums_by_kind();
+        assert_eq!(sums[&b'*'], 2472);
+        assert_eq!(sums[&b'#'], 633);
+        assert_eq!(sums[&b'+'], 592);
+        assert_eq!(sums[&b'$'], 664);
+    }
+
+    #[test]
+    fn test_gears() {
+        let input = TEST_INPUT.trim_ascii_end();
+        let schematic = Schematic::parse(input);
+        let lonely: Vec<(usize, usize)> = schematic
+            .gears(b'*', 1)
+            .map(|(symbol, _)| (symbol.row, symbol.col))
+            .collect();
+        assert_eq!(lonely, vec![(4, 3)]);
+        assert_eq!(schematic.gears(b'*', 2).count(), 2);
+    }
+
+    #[test]
+    fn test_shared_numbers() {
+        let input = b"12.\n.*.\n..$";
+        let schematic = Schematic::parse(input);
+        let shared: Vec<&Number> = schematic.shared_numbers().collect();
+        assert_eq!(shared.len(), 0);
+
+        let input = b"*12$";
+        let schematic = Schematic::parse(input);
+        let shared: Vec<&Number> = schematic.shared_numbers().collect();
+        assert_eq!(shared.len(), 1);
+        assert_eq!((shared[0].row, shared[0].col, shared[0].len), (0, 1, 2));
+        assert_eq!(shared[0].value, 12);
+    }
+
+    #[test]
+    fn test_part2_long_numbers() {
+        let input = b"1234567.\n...*....\n..98765432";
+        assert_eq!(part2(input), 1234567 * 98765432);
+
+        let input = b"12345*67890";
+        assert_eq!(part2(input), 12345 * 67890);
+
+        assert_eq!(part1(b"18446744073709551615+"), usize::MAX);
+        assert_eq!(part2(b"4294967295*4294967297"), usize::MAX);
+    }
+
+    #[test]
+    #[should_panic(expected = "number does not fit in usize")]
+    fn test_number_overflow() {
+        part1(b"18446744073709551616+");
+    }
+
+    #[test]
+    #[should_panic(expected = "gear ratio does not fit in usize")]
+    fn test_gear_ratio_overflow() {
+        part2(b"4294967296*4294967296");
+    }
+
+    fn generate_schematic(rng: &mut XorShift) -> Vec<u8> {
+        let rows = 1 + rng.next(12);
+        let cols = 1 + rng.next(24);
+        let mut result = vec![];
+        for row in 0..rows {
+            let mut col = 0;
+            while col < cols {
+                match rng.next(10) {
+                    0..=2 => {
+                        let len = (1 + rng.next(9)).min(cols - col);
+                        for _ in 0..len {
+                            result.push(b'0' + rng.next(10) as u8);
+                        }
+                        col += len;
+                        if col < cols {
+                            result.push(b'.');
+                            col += 1;
+                        }
+                        continue;
+                    }
+                    3 => result.push(b'*'),
+                    4 => result.push(b"#$+/@"[rng.next(5)]),
+                    _ => result.push(b'.'),
+                }
+                col += 1;
+            }
+            if row + 1 < rows {
+                result.push(b'\n');
+            }
+        }
+        result
+    }
+
+    fn part2_brute_force(input: &[u8]) -> usize {
+        let grid: Vec<&[u8]> = input.split(|c| *c == b'\n').collect();
+        let mut result = 0;
+        for (row, line) in grid.iter().enumerate() {
+            for (col, c) in line.iter().enumerate() {
+                if *c != b'*' {
+                    continue;
+                }
+                let mut numbers: Vec<(usize, usize, usize)> = vec![];
+                for r in row.saturating_sub(1)..=row + 1 {
+                    for c in col.saturating_sub(1)..=col + 1 {
+                        let Some(line) = grid.get(r) else { continue };
+                        if !line.get(c).is_some_and(|c| c.is_ascii_digit()) {
+                            continue;
+                        }
+                        let mut start = c;
+                        while start > 0 && line[start - 1].is_ascii_digit() {
+                            start -= 1;
+                        }
+                        let mut end = c;
+                        while end < line.len() && line[end].is_ascii_digit() {
+                            end += 1;
+                        }
+                        let value = std::str::from_utf8(&line[start..end])
+                            .unwrap()
+                            .parse()
+                            .unwrap();
+                        if !numbers.contains(&(r, start, value)) {
+                            numbers.push((r, start, value));
+                        }
+                    }
+                }
+                if numbers.len() == 2 {
+                    result += numbers[0].2 * numbers[1].2;
+                }
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn test_part2_generated() {
+        let mut rng = XorShift(0x2023_1203);
+        for _ in 0..1000 {
+            let input = generate_schematic(&mut rng);
+            assert_eq!(
+                part2(&input),
+                part2_brute_force(&input),
+                "{}",
+                String::from_utf8_lossy(&input)
+            );
+        }
+    }
+
     #[bench]
     fn bench_part1(b: &mut Bencher) {
         let input = INPUT.trim_ascii_end();
@@ -2,12 +2,22 @@ extern crate test;
 
 const INPUT: &[u8] = include_bytes!("../inputs/day04.txt");
 
-const POWERS_OF_TEN: [u32; 2] = [1, 10];
+fn number_key(digits: &[u8]) -> (usize, &[u8]) {
+    let start = digits
+        .iter()
+        .position(|c| *c != b'0')
+        .unwrap_or(digits.len());
+    (digits.len() - start, &digits[start..])
+}
 
-fn u32_from_bytes(bytes: &[u8]) -> u32 {
-    bytes.iter().rev().enumerate().fold(0, |acc, (ix, x)| {
-        acc + (x & 0x0f) as u32 * POWERS_OF_TEN[ix]
-    })
+fn parse_numbers(group: &[u8]) -> Vec<(usize, &[u8])> {
+    let mut numbers: Vec<(usize, &[u8])> = group
+        .split(|c| *c == b' ')
+        .filter(|num| !num.is_empty())
+        .map(number_key)
+        .collect();
+    numbers.sort_unstable();
+    numbers
 }
 
 fn get_winning_count(line: &[u8]) -> u32 {
@@ -15,25 +25,18 @@ fn get_winning_count(line: &[u8]) -> u32 {
     let mut groups = numbers
         .split(|c| *c == b'|')
         .map(|group| group.trim_ascii_end());
-    let winning: u128 = groups
-        .next()
-        .unwrap()
-        .split(|c| *c == b' ')
-        .filter(|num| !num.is_empty())
-        .fold(0, |acc, num| {
-            let num: u32 = u32_from_bytes(num);
-            acc | (1_u128 << (num - 1))
-        });
-    groups
-        .next()
-        .unwrap()
-        .split(|c| *c == b' ')
-        .filter(|num| !num.is_empty())
-        .filter(|num| {
-            let num: u32 = u32_from_bytes(num);
-            winning & (1_u128 << (num - 1)) != 0
-        })
-        .count() as u32
+    let winning = parse_numbers(groups.next().unwrap());
+    let have = parse_numbers(groups.next().unwrap());
+
+    let mut winning = winning.iter().peekable();
+    let mut count = 0;
+    for num in have {
+        while winning.next_if(|w| **w < num).is_some() {}
+        if winning.peek() == Some(&&num) {
+            count += 1;
+        }
+    }
+    count
 }
 
 fn part1(input: &[u8]) -> usize {
@@ -50,26 +53,63 @@ fn part1(input: &[u8]) -> usize {
         .sum()
 }
 
-fn part2(input: &[u8]) -> usize {
-    let winning_map: Vec<u32> = input
-        .split(|c| *c == b'\n')
-        .map(get_winning_count)
-        .collect();
+struct Cascade {
+    copies: Vec<usize>,
+    sources: Vec<Vec<(usize, usize)>>,
+}
+
+impl Cascade {
+    fn simulate(winning_map: &[u32], rule: impl Fn(u32) -> usize) -> Self {
+        let mut copies: Vec<usize> = vec![1; winning_map.len()];
+        let mut sources: Vec<Vec<(usize, usize)>> = vec![vec![]; winning_map.len()];
+
+        for (i, won) in winning_map.iter().enumerate() {
+            let current = copies[i];
+            let end = (i + 1 + rule(*won)).min(copies.len());
+            for target in i + 1..end {
+                copies[target] += current;
+                sources[target].push((i, current));
+            }
+        }
 
-    let mut result = 0;
+        Self { copies, sources }
+    }
 
-    let mut counts: Vec<usize> = vec![1; winning_map.len()];
+    fn total(&self) -> usize {
+        self.copies.iter().sum()
+    }
 
-    for i in 0..=counts.len() - 1 {
-        let current = counts[i];
-        result += current;
-        let won = winning_map[i];
-        for count in counts.iter_mut().skip(i).take(won as usize + 1) {
-            *count += current;
+    fn chain(&self, card: usize) -> Vec<usize> {
+        let mut visited = vec![false; card + 1];
+        let mut stack = vec![card];
+        while let Some(current) = stack.pop() {
+            for (source, _) in self.sources[current].iter() {
+                if !visited[*source] {
+                    visited[*source] = true;
+                    stack.push(*source);
+                }
+            }
         }
+        visited
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| **v)
+            .map(|(i, _)| i)
+            .collect()
     }
+}
 
-    result
+fn default_rule(won: u32) -> usize {
+    won as usize
+}
+
+fn part2(input: &[u8]) -> usize {
+    let winning_map: Vec<u32> = input
+        .split(|c| *c == b'\n')
+        .map(get_winning_count)
+        .collect();
+
+    Cascade::simulate(&winning_map, default_rule).total()
 }
 
 pub fn main() {
@@ -79,6 +119,29 @@ pub fn main() {
     println!("{}", part2(input));
 }
 
+pub fn explain() {
+    let input = INPUT.trim_ascii_end();
+    let winning_map: Vec<u32> = input
+        .split(|c| *c == b'\n')
+        .map(get_winning_count)
+        .collect();
+    let cascade = Cascade::simulate(&winning_map, default_rule);
+
+    for (card, copies) in cascade.copies.iter().enumerate() {
+        let chain: Vec<String> = cascade
+            .chain(card)
+            .iter()
+            .map(|source| (source + 1).to_string())
+            .collect();
+        println!(
+            "Card {}: {} copies from [{}]",
+            card + 1,
+            copies,
+            chain.join(", ")
+        );
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -98,6 +161,44 @@ mod tests {
         assert_eq!(part2(input), 30);
     }
 
+    #[test]
+    fn test_cascade() {
+        let input = TEST_INPUT.trim_ascii_end();
+        let winning_map: Vec<u32> = input
+            .split(|c| *c == b'\n')
+            .map(get_winning_count)
+            .collect();
+        let cascade = Cascade::simulate(&winning_map, default_rule);
+        assert_eq!(cascade.copies, vec![1, 2, 4, 8, 14, 1]);
+        assert_eq!(cascade.sources[1], vec![(0, 1)]);
+        assert_eq!(cascade.sources[4], vec![(0, 1), (2, 4), (3, 8)]);
+        assert_eq!(cascade.chain(0), vec![]);
+        assert_eq!(cascade.chain(4), vec![0, 1, 2, 3]);
+        assert_eq!(cascade.chain(5), vec![]);
+
+        let cascade = Cascade::simulate(&winning_map, |won| (won as usize).min(1));
+        assert_eq!(cascade.copies, vec![1, 2, 3, 4, 5, 1]);
+        assert_eq!(cascade.total(), 16);
+    }
+
+    #[test]
+    fn test_winning_count_large_numbers() {
+        assert_eq!(get_winning_count(b"Card 1: 0 128 9999 | 9999 0 127 128"), 3);
+        assert_eq!(get_winning_count(b"Card 2: 0 | 1 64 128"), 0);
+        assert_eq!(get_winning_count(b"Card 3: 63 64 | 64 65 63"), 2);
+        assert_eq!(
+            get_winning_count(b"Card 4: 100000 4294967295 | 4294967295 100000 99999"),
+            2
+        );
+        assert_eq!(get_winning_count(b"Card 5: 0 1 | 4294967296 4294967297"), 0);
+        assert_eq!(
+            get_winning_count(
+                b"Card 6: 4294967296 340282366920938463463374607431768211456 | 04294967296 340282366920938463463374607431768211457"
+            ),
+            1
+        );
+    }
+
     #[bench]
     fn bench_part1(b: &mut Bencher) {
         let input = INPUT.trim_ascii_end();
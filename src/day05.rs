@@ -1,177 +1,330 @@
 extern crate test;
 
+use std::collections::{HashMap, VecDeque};
+
 const INPUT: &str = include_str!("../inputs/day05.txt");
 
-fn part1(input: &str) -> usize {
-    let mut groups = input.split("\n\n");
-    let seeds: Vec<usize> = groups
-        .next()
-        .unwrap()
-        .split(": ")
-        .nth(1)
-        .unwrap()
-        .split_whitespace()
-        .map(|num| num.parse().unwrap())
-        .collect();
-    let maps: Vec<Vec<(usize, usize, usize)>> = groups
-        .map(|group| {
-            let mut map: Vec<(usize, usize, usize)> = group
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Segment {
+    start: usize,
+    end: usize,
+    offset: isize,
+}
+
+impl Segment {
+    fn image_start(&self) -> usize {
+        self.start.checked_add_signed(self.offset).unwrap()
+    }
+
+    fn image_end(&self) -> usize {
+        self.end
+            .checked_add_signed(self.offset)
+            .unwrap_or(usize::MAX)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PiecewiseMap {
+    segments: Vec<Segment>,
+}
+
+impl PiecewiseMap {
+    fn identity() -> Self {
+        Self {
+            segments: vec![Segment {
+                start: 0,
+                end: usize::MAX,
+                offset: 0,
+            }],
+        }
+    }
+
+    fn from_segments(mut segments: Vec<Segment>) -> Self {
+        segments.sort_by_key(|segment| segment.start);
+
+        let mut result: Vec<Segment> = vec![];
+        let mut position = 0;
+
+        for segment in segments {
+            if segment.start > position {
+                result.push(Segment {
+                    start: position,
+                    end: segment.start,
+                    offset: 0,
+                });
+            }
+            result.push(segment);
+            position = segment.end;
+        }
+
+        if position < usize::MAX {
+            result.push(Segment {
+                start: position,
+                end: usize::MAX,
+                offset: 0,
+            });
+        }
+
+        let mut merged: Vec<Segment> = vec![];
+        for segment in result {
+            match merged.last_mut() {
+                Some(last) if last.offset == segment.offset && last.end == segment.start => {
+                    last.end = segment.end;
+                }
+                _ => merged.push(segment),
+            }
+        }
+
+        Self { segments: merged }
+    }
+
+    fn parse(lines: &str) -> Self {
+        Self::from_segments(
+            lines
                 .lines()
-                .skip(1)
                 .map(|line| {
-                    let mut nums = line.split_whitespace().map(|num| num.parse().unwrap());
+                    let mut nums = line
+                        .split_whitespace()
+                        .map(|num| num.parse::<usize>().unwrap());
                     let to = nums.next().unwrap();
                     let from = nums.next().unwrap();
                     let count = nums.next().unwrap();
-                    (from, to, count)
-                })
-                .collect();
-            map.sort_by_key(|(a, _, _)| *a);
-            if map[0].0 != 0 {
-                map.push((0, 0, map[0].0));
-                map.sort_by_key(|(a, _, _)| *a);
-            }
-            map
-        })
-        .collect();
-
-    seeds
-        .iter()
-        .map(|seed| {
-            maps.iter().fold(*seed, |acc, map| {
-                let entry = match map.binary_search_by(|(a, _, _)| a.cmp(&acc)) {
-                    Ok(index) => index,
-                    Err(index) => {
-                        if index == 0 {
-                            0
-                        } else {
-                            index - 1
-                        }
+                    Segment {
+                        start: from,
+                        end: from + count,
+                        offset: to as isize - from as isize,
                     }
-                };
-                let (from, to, count) = map[entry];
-                let diff = acc - from;
-                if diff < count {
-                    to + diff
-                } else {
-                    acc
-                }
+                })
+                .collect(),
+        )
+    }
+
+    fn segment_index(&self, value: usize) -> usize {
+        self.segments
+            .partition_point(|segment| segment.end <= value)
+    }
+
+    fn apply(&self, value: usize) -> usize {
+        let segment = self.segments[self.segment_index(value)];
+        value.checked_add_signed(segment.offset).unwrap()
+    }
+
+    fn apply_range(&self, start: usize, end: usize) -> impl Iterator<Item = Segment> + '_ {
+        self.segments[self.segment_index(start)..]
+            .iter()
+            .take_while(move |segment| segment.start < end)
+            .map(move |segment| Segment {
+                start: start.max(segment.start),
+                end: end.min(segment.end),
+                offset: segment.offset,
             })
-        })
-        .min()
-        .unwrap()
+    }
+
+    fn compose(&self, next: &Self) -> Self {
+        let segments = self
+            .segments
+            .iter()
+            .flat_map(|segment| {
+                next.apply_range(segment.image_start(), segment.image_end())
+                    .map(|piece| Segment {
+                        start: piece.start.checked_add_signed(-segment.offset).unwrap(),
+                        end: piece
+                            .end
+                            .checked_add_signed(-segment.offset)
+                            .unwrap_or(usize::MAX),
+                        offset: segment.offset + piece.offset,
+                    })
+            })
+            .collect();
+
+        Self::from_segments(segments)
+    }
+
+    fn invert(&self) -> Option<Self> {
+        let mut segments: Vec<Segment> = self
+            .segments
+            .iter()
+            .map(|segment| Segment {
+                start: segment.image_start(),
+                end: segment.image_end(),
+                offset: -segment.offset,
+            })
+            .collect();
+        segments.sort_by_key(|segment| segment.start);
+
+        if segments[0].start != 0 || segments.windows(2).any(|pair| pair[0].end != pair[1].start) {
+            return None;
+        }
+
+        Some(Self::from_segments(segments))
+    }
+
+    fn min_over(&self, ranges: &[(usize, usize)]) -> Option<usize> {
+        ranges
+            .iter()
+            .flat_map(|(start, end)| self.apply_range(*start, *end))
+            .map(|piece| piece.image_start())
+            .min()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum AlmanacError {
+    MissingLink(String, String),
+    CyclicLink(String),
+    NotInvertible(String, String),
+}
+
+struct Almanac {
+    seeds: Vec<usize>,
+    maps: HashMap<String, Vec<(String, PiecewiseMap)>>,
 }
 
-fn process_seed_range(
-    from: usize,
-    to: usize,
-    map: &[(usize, isize, usize)],
-) -> Vec<(usize, usize)> {
-    let from_index = match map.binary_search_by(|(a, _, _)| a.cmp(&from)) {
-        Ok(index) => index,
-        Err(index) => {
-            if index == 0 {
-                0
-            } else {
-                index - 1
+impl Almanac {
+    fn parse(input: &str) -> Result<Self, AlmanacError> {
+        let mut groups = input.split("\n\n");
+        let seeds: Vec<usize> = groups
+            .next()
+            .unwrap()
+            .split(": ")
+            .nth(1)
+            .unwrap()
+            .split_whitespace()
+            .map(|num| num.parse().unwrap())
+            .collect();
+
+        let mut maps: HashMap<String, Vec<(String, PiecewiseMap)>> = HashMap::new();
+        for group in groups {
+            let (header, lines) = group.split_once('\n').unwrap_or((group, ""));
+            let (from, to) = header
+                .strip_suffix(" map:")
+                .and_then(|name| name.split_once("-to-"))
+                .unwrap();
+            maps.entry(from.to_string())
+                .or_default()
+                .push((to.to_string(), PiecewiseMap::parse(lines)));
+        }
+
+        let almanac = Self { seeds, maps };
+        almanac.check_acyclic()?;
+
+        Ok(almanac)
+    }
+
+    fn check_acyclic(&self) -> Result<(), AlmanacError> {
+        let mut finished: HashMap<&str, bool> = HashMap::new();
+
+        for category in self.maps.keys() {
+            let mut stack: Vec<(&str, usize)> = vec![(category, 0)];
+            while let Some((current, index)) = stack.pop() {
+                if index == 0 {
+                    match finished.get(current) {
+                        Some(true) => continue,
+                        Some(false) => return Err(AlmanacError::CyclicLink(current.to_string())),
+                        None => {
+                            finished.insert(current, false);
+                        }
+                    }
+                }
+                match self.maps.get(current).and_then(|edges| edges.get(index)) {
+                    Some((next, _)) => {
+                        stack.push((current, index + 1));
+                        stack.push((next, 0));
+                    }
+                    None => {
+                        finished.insert(current, true);
+                    }
+                }
             }
         }
-    };
-
-    let to_index = match map.binary_search_by(|(a, _, _)| a.cmp(&to)) {
-        Ok(index) => index,
-        Err(index) => {
-            if index == 0 {
-                0
-            } else {
-                index - 1
+
+        Ok(())
+    }
+
+    fn find_path(&self, from: &str, to: &str) -> Option<Vec<(&str, &PiecewiseMap)>> {
+        let mut previous: HashMap<&str, (&str, &PiecewiseMap)> = HashMap::new();
+        let mut queue: VecDeque<&str> = VecDeque::from([from]);
+
+        while let Some(current) = queue.pop_front() {
+            if current == to {
+                let mut path = vec![];
+                let mut category = to;
+                while category != from {
+                    let (name, (prev, map)) = previous.get_key_value(category).unwrap();
+                    path.push((*name, *map));
+                    category = prev;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for (next, map) in self.maps.get(current).into_iter().flatten() {
+                if next != from && !previous.contains_key(next.as_str()) {
+                    previous.insert(next, (current, map));
+                    queue.push_back(next);
+                }
             }
         }
-    };
-
-    let mut result: Vec<(usize, usize)> = vec![];
-
-    for index in from_index..=to_index {
-        let map_entry = map[index];
-        let map_range_start = map_entry.0;
-        let diff = map_entry.1;
-        let map_range_count = map_entry.2;
-        let map_range_end = map_range_start + map_range_count - 1;
-
-        if from <= map_range_end {
-            let this_range_start = std::cmp::max(from, map_range_start);
-            let this_range_end = std::cmp::min(map_range_end, to);
-            result.push((
-                (this_range_start as isize + diff) as usize,
-                (this_range_end as isize + diff) as usize,
-            ));
+
+        None
+    }
+
+    fn map(&self, from: &str, to: &str) -> Result<PiecewiseMap, AlmanacError> {
+        if let Some(path) = self.find_path(from, to) {
+            return Ok(path
+                .into_iter()
+                .fold(PiecewiseMap::identity(), |acc, (_, map)| acc.compose(map)));
         }
 
-        if to > map_range_end {
-            let this_range_start = std::cmp::max(map_range_end + 1, from);
-            let this_range_end = if index + 1 < map.len() {
-                std::cmp::min(to, map[index + 1].0 - 1)
-            } else {
-                to
-            };
-            if this_range_end >= this_range_start {
-                result.push((this_range_start, this_range_end));
-            }
+        match self.find_path(to, from) {
+            Some(path) => path
+                .into_iter()
+                .fold(PiecewiseMap::identity(), |acc, (_, map)| acc.compose(map))
+                .invert()
+                .ok_or_else(|| AlmanacError::NotInvertible(to.to_string(), from.to_string())),
+            None => Err(AlmanacError::MissingLink(from.to_string(), to.to_string())),
         }
     }
 
-    result.sort_by_key(|(a, _)| *a);
+    fn trace<'a>(
+        &'a self,
+        from: &'a str,
+        to: &str,
+        value: usize,
+    ) -> Result<Vec<(&'a str, usize)>, AlmanacError> {
+        let path = self
+            .find_path(from, to)
+            .ok_or_else(|| AlmanacError::MissingLink(from.to_string(), to.to_string()))?;
 
-    result
+        let mut result = vec![(from, value)];
+        for (category, map) in path {
+            let value = map.apply(result.last().unwrap().1);
+            result.push((category, value));
+        }
+
+        Ok(result)
+    }
 }
 
-fn part2(input: &str) -> usize {
-    let mut groups = input.split("\n\n");
-    let seeds_input: Vec<usize> = groups
-        .next()
-        .unwrap()
-        .split(": ")
-        .nth(1)
-        .unwrap()
-        .split_whitespace()
-        .map(|num| num.parse().unwrap())
-        .collect();
-    let mut seed_ranges: Vec<(usize, usize)> = seeds_input
-        .chunks(2)
-        .map(|chunk| (chunk[0], chunk[0] + chunk[1] - 1))
-        .collect();
-    seed_ranges.sort_by_key(|(start, _)| *start);
-    let maps: Vec<Vec<(usize, isize, usize)>> = groups
-        .map(|group| {
-            let mut map: Vec<(usize, isize, usize)> = group
-                .lines()
-                .skip(1)
-                .map(|line| {
-                    let mut nums = line.split_whitespace().map(|num| num.parse().unwrap());
-                    let to = nums.next().unwrap();
-                    let from = nums.next().unwrap();
-                    let count = nums.next().unwrap();
-                    (from, (to as isize - from as isize), count)
-                })
-                .collect();
-            map.sort_by_key(|(a, _, _)| *a);
-            if map[0].0 != 0 {
-                map.push((0, 0, map[0].0));
-                map.sort_by_key(|(a, _, _)| *a);
-            }
-            map
-        })
-        .collect();
+fn solve(input: &str, get_ranges: fn(&[usize]) -> Vec<(usize, usize)>) -> usize {
+    let almanac = Almanac::parse(input).unwrap();
+    let map = almanac.map("seed", "location").unwrap();
 
-    for map in maps {
-        let mut new_seed_ranges: Vec<(usize, usize)> = seed_ranges
-            .iter()
-            .flat_map(|range| process_seed_range(range.0, range.1, &map))
-            .collect();
-        std::mem::swap(&mut seed_ranges, &mut new_seed_ranges);
-    }
+    map.min_over(&get_ranges(&almanac.seeds)).unwrap()
+}
+
+fn part1(input: &str) -> usize {
+    solve(input, |seeds| {
+        seeds.iter().map(|seed| (*seed, *seed + 1)).collect()
+    })
+}
 
-    seed_ranges.iter().map(|range| range.0).min().unwrap()
+fn part2(input: &str) -> usize {
+    solve(input, |seeds| {
+        seeds
+            .chunks(2)
+            .map(|chunk| (chunk[0], chunk[0] + chunk[1]))
+            .collect()
+    })
 }
 
 pub fn main() {
@@ -181,6 +334,21 @@ pub fn main() {
     println!("{}", part2(input));
 }
 
+pub fn explain() {
+    let input = INPUT.trim_end();
+    let almanac = Almanac::parse(input).unwrap();
+
+    for seed in almanac.seeds.iter() {
+        let steps: Vec<String> = almanac
+            .trace("seed", "location", *seed)
+            .unwrap()
+            .iter()
+            .map(|(category, value)| format!("{} {}", category, value))
+            .collect();
+        println!("{}", steps.join(" -> "));
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -200,6 +368,111 @@ mod tests {
         assert_eq!(part2(input), 46);
     }
 
+    #[test]
+    fn test_composed_map() {
+        let input = TEST_INPUT.trim_end();
+        let almanac = Almanac::parse(input).unwrap();
+        let map = almanac.map("seed", "location").unwrap();
+        let locations: Vec<usize> = almanac.seeds.iter().map(|seed| map.apply(*seed)).collect();
+        assert_eq!(locations, vec![82, 43, 86, 35]);
+        assert_eq!(map.min_over(&[(79, 93), (55, 68)]), Some(46));
+        assert_eq!(map.min_over(&[(82, 83)]), Some(46));
+        assert_eq!(map.min_over(&[]), None);
+    }
+
+    #[test]
+    fn test_inverted_map() {
+        let input = TEST_INPUT.trim_end();
+        let almanac = Almanac::parse(input).unwrap();
+        let map = almanac.map("seed", "location").unwrap();
+        let inverse = map.invert().unwrap();
+        assert_eq!(almanac.map("location", "seed"), Ok(inverse.clone()));
+        assert_eq!(inverse.apply(46), 82);
+        assert_eq!(inverse.apply(35), 13);
+        for location in 0..200 {
+            assert_eq!(map.apply(inverse.apply(location)), location);
+        }
+        assert_eq!(inverse.invert(), Some(map));
+    }
+
+    #[test]
+    fn test_named_categories() {
+        let input = TEST_INPUT.trim_end();
+        let mut groups: Vec<&str> = input.split("\n\n").collect();
+        groups[1..].reverse();
+        let reordered = groups.join("\n\n");
+        assert_eq!(part1(&reordered), 35);
+        assert_eq!(part2(&reordered), 46);
+
+        let almanac = Almanac::parse(&reordered).unwrap();
+        assert_eq!(almanac.map("seed", "humidity").unwrap().apply(79), 78);
+        assert_eq!(almanac.map("water", "location").unwrap().apply(81), 82);
+        assert_eq!(almanac.map("soil", "soil"), Ok(PiecewiseMap::identity()));
+        assert_eq!(
+            almanac.map("seed", "weather"),
+            Err(AlmanacError::MissingLink(
+                "seed".to_string(),
+                "weather".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_trace() {
+        let input = TEST_INPUT.trim_end();
+        let almanac = Almanac::parse(input).unwrap();
+        assert_eq!(
+            almanac.trace("seed", "location", 79),
+            Ok(vec![
+                ("seed", 79),
+                ("soil", 81),
+                ("fertilizer", 81),
+                ("water", 81),
+                ("light", 74),
+                ("temperature", 78),
+                ("humidity", 78),
+                ("location", 82)
+            ])
+        );
+        assert_eq!(
+            almanac.trace("light", "humidity", 74),
+            Ok(vec![("light", 74), ("temperature", 78), ("humidity", 78)])
+        );
+        assert_eq!(
+            almanac.trace("location", "seed", 82),
+            Err(AlmanacError::MissingLink(
+                "location".to_string(),
+                "seed".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_cyclic_categories() {
+        let input = "seeds: 1\n\na-to-b map:\n0 1 1\n\nb-to-c map:\n1 0 1\n\nc-to-a map:\n5 5 1";
+        assert!(matches!(
+            Almanac::parse(input),
+            Err(AlmanacError::CyclicLink(_))
+        ));
+    }
+
+    #[test]
+    fn test_non_invertible_map() {
+        let map = PiecewiseMap::parse("10 0 5");
+        assert_eq!(map.apply(3), 13);
+        assert_eq!(map.apply(13), 13);
+        assert_eq!(map.invert(), None);
+
+        let almanac = Almanac::parse("seeds: 1\n\na-to-b map:\n10 0 5").unwrap();
+        assert_eq!(
+            almanac.map("b", "a"),
+            Err(AlmanacError::NotInvertible(
+                "a".to_string(),
+                "b".to_string()
+            ))
+        );
+    }
+
     #[bench]
     fn bench_part1(b: &mut Bencher) {
         let input = INPUT.trim_end();
@@ -1,16 +1,79 @@
 extern crate test;
 
+use num::integer::Roots;
+use num::Integer;
+
 const INPUT: &[u8] = include_bytes!("../inputs/day06.txt");
 
-fn collect_as_u32<'a>(it: impl Iterator<Item = &'a u8>) -> u32 {
-    it.fold(0, |acc, c| acc * 10 + (c - b'0') as u32)
+fn collect_as<'a, T: Integer + From<u8>>(it: impl Iterator<Item = &'a u8>) -> T {
+    it.fold(T::zero(), |acc, c| acc * T::from(10) + T::from(c - b'0'))
+}
+
+fn winning_interval<T: Integer + Roots + Clone>(t: &T, s: &T) -> Option<(T, T)> {
+    let two = T::one() + T::one();
+    let wins = |h: &T| h.clone() * (t.clone() - h.clone()) > *s;
+
+    let half = t.clone() / two.clone();
+    if !wins(&half) {
+        return None;
+    }
+
+    let d = (t.clone() * t.clone() - two.clone() * two.clone() * s.clone()).sqrt();
+    let mut h = (t.clone() - d) / two;
+    while h > T::zero() && wins(&(h.clone() - T::one())) {
+        h = h - T::one();
+    }
+    while !wins(&h) {
+        h = h + T::one();
+    }
+
+    Some((h.clone(), t.clone() - h))
 }
 
-fn collect_as_usize<'a>(it: impl Iterator<Item = &'a u8>) -> usize {
-    it.fold(0, |acc, c| acc * 10 + (c - b'0') as usize)
+#[derive(Debug, Clone)]
+struct RaceModel<T> {
+    acceleration: T,
+    delay: T,
+    min_hold: T,
 }
 
-fn part1(input: &[u8]) -> usize {
+impl<T: Integer + Roots + Clone> RaceModel<T> {
+    fn standard() -> Self {
+        Self {
+            acceleration: T::one(),
+            delay: T::zero(),
+            min_hold: T::zero(),
+        }
+    }
+
+    fn winning_interval(&self, time: &T, record: &T) -> Option<(T, T)> {
+        if self.acceleration.is_zero() || *time < self.delay {
+            return None;
+        }
+
+        let t = time.clone() - self.delay.clone();
+        let s = record.clone() / self.acceleration.clone();
+        let (low, high) = winning_interval(&t, &s)?;
+        let low = low.max(self.min_hold.clone());
+
+        if low <= high {
+            Some((low, high))
+        } else {
+            None
+        }
+    }
+
+    fn count_winning(&self, time: &T, record: &T) -> T {
+        self.winning_interval(time, record)
+            .map(|(low, high)| high - low + T::one())
+            .unwrap_or_else(T::zero)
+    }
+}
+
+fn solve_races<T: Integer + Roots + Clone + From<u8>>(
+    input: &[u8],
+    model: &RaceModel<T>,
+) -> Vec<Option<(T, T)>> {
     let mut lines = input.split(|c| *c == b'\n');
     let durations = lines
         .next()
@@ -18,36 +81,33 @@ fn part1(input: &[u8]) -> usize {
         .split(|c| *c == b' ')
         .skip(1)
         .filter(|group| !group.is_empty())
-        .map(|num| collect_as_u32(num.iter()));
+        .map(|num| collect_as::<T>(num.iter()));
     let times = lines
         .next()
         .unwrap()
         .split(|c| *c == b' ')
         .skip(1)
         .filter(|group| !group.is_empty())
-        .map(|num| collect_as_u32(num.iter()));
+        .map(|num| collect_as::<T>(num.iter()));
 
     std::iter::zip(durations, times)
-        .map(|(t, s)| {
-            let d = ((t * t - 4 * s) as f64).sqrt();
-            let t = t as f64;
-            ((t + d - 2.0) / 2.0).ceil() as usize - ((t - d + 2.0) / 2.0).floor() as usize + 1
-        })
+        .map(|(t, s)| model.winning_interval(&t, &s))
+        .collect()
+}
+
+fn part1(input: &[u8]) -> usize {
+    solve_races::<u128>(input, &RaceModel::standard())
+        .into_iter()
+        .map(|interval| interval.map_or(0, |(low, high)| (high - low + 1) as usize))
         .product()
 }
 
 fn part2(input: &[u8]) -> usize {
     let mut lines = input.split(|c| *c == b'\n');
-    let t: usize = collect_as_usize(lines.next().unwrap().iter().filter(|c| c.is_ascii_digit()));
-    let s: usize = collect_as_usize(lines.next().unwrap().iter().filter(|c| c.is_ascii_digit()));
-
-    let d = ((t * t - 4 * s) as f64).sqrt();
-    let t = t as f64;
-
-    let a = (t - d + 2.0) / 2.0;
-    let b = (t + d - 2.0) / 2.0;
+    let t: u128 = collect_as(lines.next().unwrap().iter().filter(|c| c.is_ascii_digit()));
+    let s: u128 = collect_as(lines.next().unwrap().iter().filter(|c| c.is_ascii_digit()));
 
-    b.ceil() as usize - a.floor() as usize + 1
+    RaceModel::standard().count_winning(&t, &s) as usize
 }
 
 pub fn main() {
@@ -60,6 +120,7 @@ pub fn main() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use num::BigUint;
     use test::Bencher;
 
     const TEST_INPUT: &[u8] = include_bytes!("../test_inputs/day06.txt");
@@ -76,6 +137,94 @@ mod tests {
         assert_eq!(part2(input), 71503);
     }
 
+    fn count_winning_brute_force(t: u128, s: u128) -> u128 {
+        (0..=t).filter(|h| h * (t - h) > s).count() as u128
+    }
+
+    #[test]
+    fn test_count_winning_brute_force() {
+        for t in 0..64_u128 {
+            for s in 0..=t * t / 4 + 2 {
+                assert_eq!(
+                    RaceModel::standard().count_winning(&t, &s),
+                    count_winning_brute_force(t, s)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_race_intervals() {
+        let input = TEST_INPUT.trim_ascii_end();
+        assert_eq!(
+            solve_races::<u128>(input, &RaceModel::standard()),
+            vec![Some((2, 5)), Some((4, 11)), Some((11, 19))]
+        );
+
+        let model = RaceModel {
+            acceleration: 2,
+            delay: 1,
+            min_hold: 3,
+        };
+        assert_eq!(
+            solve_races::<u128>(input, &model),
+            vec![Some((3, 5)), Some((3, 12)), Some((5, 24))]
+        );
+    }
+
+    #[test]
+    fn test_race_model_brute_force() {
+        for acceleration in 0..4_u128 {
+            for delay in 0..4 {
+                for min_hold in 0..4 {
+                    let model = RaceModel {
+                        acceleration,
+                        delay,
+                        min_hold,
+                    };
+                    for t in 0..24_u128 {
+                        for s in 0..=t * t {
+                            let expected: Vec<u128> = (min_hold..=t)
+                                .filter(|h| {
+                                    t >= h + delay && acceleration * h * (t - h - delay) > s
+                                })
+                                .collect();
+                            let interval = expected
+                                .first()
+                                .map(|low| (*low, *expected.last().unwrap()));
+                            assert_eq!(model.winning_interval(&t, &s), interval);
+                            assert_eq!(model.count_winning(&t, &s), expected.len() as u128);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_count_winning_large() {
+        let t: u128 = u64::MAX as u128;
+        let s: u128 = (t / 2) * (t - t / 2) - 1;
+        assert_eq!(winning_interval(&t, &s), Some((t / 2, t - t / 2)));
+
+        let t: BigUint = "123456789012345678901234567890".parse().unwrap();
+        let h: BigUint = "1000000000000000000000000".parse().unwrap();
+        let s: BigUint = &h * (&t - &h);
+        let expected = &t - 2_u32 * (&h + 1_u32) + 1_u32;
+        assert_eq!(RaceModel::standard().count_winning(&t, &s), expected);
+
+        let input =
+            b"Time: 3000000000000000000000\nDistance: 2250000000000000000000000000000000000000000";
+        let mut lines = input.split(|c| *c == b'\n');
+        let t: BigUint = collect_as(lines.next().unwrap().iter().filter(|c| c.is_ascii_digit()));
+        let s: BigUint = collect_as(lines.next().unwrap().iter().filter(|c| c.is_ascii_digit()));
+        assert_eq!(winning_interval(&t, &s), None);
+        assert_eq!(
+            RaceModel::standard().count_winning(&t, &(s - 1_u32)),
+            BigUint::from(1_u32)
+        );
+    }
+
     #[bench]
     fn bench_part1(b: &mut Bencher) {
         let input = INPUT.trim_ascii_end();
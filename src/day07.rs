@@ -1,144 +1,320 @@
 extern crate test;
 
+use std::collections::HashMap;
+use std::fmt;
+
 const INPUT: &[u8] = include_bytes!("../inputs/day07.txt");
 
-fn get_value(hand: &mut [u8]) -> u8 {
-    hand.sort();
-    let mut counts: Vec<u8> = vec![];
-    let mut current_count: u8 = 1;
-    let mut previous = hand[0];
-    for card in &hand[1..] {
-        if *card == previous {
-            current_count += 1;
-        } else {
-            counts.push(current_count);
-            current_count = 1;
-            previous = *card;
+fn partitions(n: u8, max: u8) -> Vec<Vec<u8>> {
+    if n == 0 {
+        return vec![vec![]];
+    }
+    (1..=max.min(n))
+        .flat_map(|first| {
+            partitions(n - first, first)
+                .into_iter()
+                .map(move |rest| std::iter::once(first).chain(rest).collect::<Vec<u8>>())
+        })
+        .collect()
+}
+
+const MAX_HAND_SIZE: usize = 15;
+
+#[derive(Debug, Clone)]
+struct RuleSet {
+    card_ranks: [Option<u8>; 256],
+    wildcards: [bool; 256],
+    hand_size: usize,
+    hand_types: Vec<Vec<u8>>,
+    type_ranks: HashMap<Vec<u8>, usize>,
+    best_types: HashMap<Vec<u8>, usize>,
+}
+
+impl RuleSet {
+    fn new(card_order: &[u8], wildcards: &[u8], hand_size: usize) -> Self {
+        let mut hand_types = partitions(hand_size as u8, hand_size as u8);
+        hand_types.sort();
+
+        Self::with_hand_types(card_order, wildcards, hand_size, hand_types)
+    }
+
+    fn with_hand_types(
+        card_order: &[u8],
+        wildcards: &[u8],
+        hand_size: usize,
+        hand_types: Vec<Vec<u8>>,
+    ) -> Self {
+        assert!(hand_size <= MAX_HAND_SIZE && card_order.len() <= 256);
+
+        let mut expected_types = partitions(hand_size as u8, hand_size as u8);
+        expected_types.sort();
+        let mut given_types = hand_types.clone();
+        given_types.sort();
+        assert_eq!(given_types, expected_types);
+
+        let mut card_ranks = [None; 256];
+        for (rank, card) in card_order.iter().enumerate() {
+            card_ranks[*card as usize] = Some(rank as u8);
+        }
+
+        let mut is_wildcard = [false; 256];
+        for card in wildcards {
+            is_wildcard[*card as usize] = true;
         }
+
+        let type_ranks = hand_types
+            .iter()
+            .enumerate()
+            .map(|(rank, hand_type)| (hand_type.clone(), rank))
+            .collect();
+
+        let mut rules = Self {
+            card_ranks,
+            wildcards: is_wildcard,
+            hand_size,
+            hand_types,
+            type_ranks,
+            best_types: HashMap::new(),
+        };
+
+        let min_cards = if wildcards.is_empty() { hand_size } else { 0 };
+        rules.best_types = (min_cards..=hand_size)
+            .flat_map(|cards| partitions(cards as u8, cards as u8))
+            .map(|counts| {
+                let mut best = None;
+                let wild = (hand_size - counts.iter().sum::<u8>() as usize) as u8;
+                rules.upgrade(&mut counts.clone(), wild, &mut best);
+                (counts, best.unwrap())
+            })
+            .collect();
+
+        rules
     }
-    counts.push(current_count);
-    counts.sort();
-    match counts.len() {
-        5 => 1,
-        4 => 2,
-        3 => {
-            if counts[2] == 2 {
-                3
-            } else {
-                4
-            }
+
+    fn standard() -> Self {
+        Self::new(b"23456789TJQKA", b"", 5)
+    }
+
+    fn with_joker() -> Self {
+        Self::new(b"J23456789TQKA", b"J", 5)
+    }
+
+    fn card_rank(&self, card: u8) -> u8 {
+        self.card_ranks[card as usize].unwrap()
+    }
+
+    fn is_wildcard(&self, card: u8) -> bool {
+        self.wildcards[card as usize]
+    }
+
+    fn type_rank(&self, hand_type: &[u8]) -> usize {
+        self.type_ranks[hand_type]
+    }
+
+    fn group_counts(cards: impl Iterator<Item = u8>) -> Vec<u8> {
+        let mut cards: Vec<u8> = cards.collect();
+        cards.sort_unstable();
+        let mut counts: Vec<u8> = cards
+            .chunk_by(|a, b| a == b)
+            .map(|group| group.len() as u8)
+            .collect();
+        counts.sort_unstable_by(|a, b| b.cmp(a));
+        counts
+    }
+
+    fn hand_type(&self, cards: &[u8]) -> Vec<u8> {
+        Self::group_counts(cards.iter().cloned())
+    }
+
+    fn best_type_rank(&self, cards: &[u8]) -> usize {
+        let mut ranks = [0; MAX_HAND_SIZE];
+        let mut len = 0;
+        for card in cards.iter().filter(|card| !self.is_wildcard(**card)) {
+            ranks[len] = self.card_rank(*card);
+            len += 1;
+        }
+        ranks[..len].sort_unstable();
+
+        let mut counts = [0; MAX_HAND_SIZE];
+        let mut groups = 0;
+        for group in ranks[..len].chunk_by(|a, b| a == b) {
+            counts[groups] = group.len() as u8;
+            groups += 1;
         }
-        2 => {
-            if counts[0] == 2 {
-                5
-            } else {
-                6
+        counts[..groups].sort_unstable_by(|a, b| b.cmp(a));
+
+        self.best_types[&counts[..groups]]
+    }
+
+    fn best_hand_type(&self, cards: &[u8]) -> Vec<u8> {
+        self.hand_types[self.best_type_rank(cards)].clone()
+    }
+
+    fn upgrade(&self, counts: &mut Vec<u8>, wild: u8, best: &mut Option<usize>) {
+        if wild == 0 {
+            let mut hand_type = counts.clone();
+            hand_type.sort_unstable_by(|a, b| b.cmp(a));
+            let rank = self.type_rank(&hand_type);
+            if best.is_none_or(|best_rank| rank > best_rank) {
+                *best = Some(rank);
             }
+            return;
         }
-        1 => 7,
-        _ => panic!(),
-    }
-}
-
-fn get_value_with_joker(hand: &mut [u8]) -> u8 {
-    let value = get_value(hand);
-    let num_jokers = hand.iter().filter(|c| **c == b'J').count();
-    if num_jokers == 0 {
-        value
-    } else {
-        match value {
-            7 => 7,
-            6 => 7,
-            5 => 7,
-            4 => 6,
-            3 => {
-                if num_jokers == 1 {
-                    5
-                } else {
-                    6
-                }
+
+        for i in 0..counts.len() {
+            if i > 0 && counts[i] == counts[i - 1] {
+                continue;
             }
-            2 => 4,
-            1 => 2,
-            _ => panic!(),
+            counts[i] += 1;
+            self.upgrade(counts, wild - 1, best);
+            counts[i] -= 1;
         }
+
+        counts.push(1);
+        self.upgrade(counts, wild - 1, best);
+        counts.pop();
     }
 }
 
-fn part1(input: &[u8]) -> usize {
-    let mut hands: Vec<u64> = input
-        .split(|c| *c == b'\n')
-        .map(|line| {
-            let entry = 0_u64;
-            let hand = line.iter().take(5).enumerate().fold(entry, |acc, (i, c)| {
-                let bits: u64 = match c {
-                    b'2'..=b'9' => (c - b'2') as u64,
-                    b'T' => 8,
-                    b'J' => 9,
-                    b'Q' => 10,
-                    b'K' => 11,
-                    b'A' => 12,
-                    _ => panic!(),
-                } << (64 - 4 * (i + 2));
-                acc | bits
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Hand {
+    key: u128,
+    bid: usize,
+}
+
+impl Hand {
+    fn parse(line: &[u8], rules: &RuleSet) -> Self {
+        let hand = &line[..rules.hand_size];
+        let key = hand
+            .iter()
+            .fold(rules.best_type_rank(hand) as u128, |key, c| {
+                key << 8 | rules.card_rank(*c) as u128
             });
-            let mut hand_buf = [0; 5];
-            hand_buf.copy_from_slice(&line[0..=4]);
-            let value = (get_value(&mut hand_buf) as u64) << 60;
-            let bid = line
-                .iter()
-                .skip(6)
-                .fold(0, |acc, digit| 10 * acc + (digit - b'0') as u64);
-            value | hand | bid
-        })
-        .collect();
+        let bid = line
+            .iter()
+            .skip(rules.hand_size + 1)
+            .fold(0, |acc, digit| 10 * acc + (digit - b'0') as usize);
 
-    hands.sort();
+        Self { key, bid }
+    }
+}
 
-    hands
-        .iter()
-        .enumerate()
-        .map(|(index, bid)| (index + 1) * (bid & 0xfff) as usize)
-        .sum()
+fn type_name(hand_type: &[u8]) -> String {
+    match hand_type {
+        [5] => "Five of a kind".to_string(),
+        [4, 1] => "Four of a kind".to_string(),
+        [3, 2] => "Full house".to_string(),
+        [3, 1, 1] => "Three of a kind".to_string(),
+        [2, 2, 1] => "Two pair".to_string(),
+        [2, 1, 1, 1] => "One pair".to_string(),
+        [1, 1, 1, 1, 1] => "High card".to_string(),
+        _ => hand_type
+            .iter()
+            .map(|count| count.to_string())
+            .collect::<Vec<String>>()
+            .join("+"),
+    }
 }
 
-fn part2(input: &[u8]) -> usize {
-    let mut hands: Vec<u64> = input
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ReportRow {
+    rank: usize,
+    hand: String,
+    hand_type: Vec<u8>,
+    best_type: Vec<u8>,
+    bid: usize,
+    winnings: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Report {
+    rows: Vec<ReportRow>,
+}
+
+impl Report {
+    fn explain(input: &[u8], rules: &RuleSet) -> Self {
+        let mut hands: Vec<(Hand, &[u8])> = input
+            .split(|c| *c == b'\n')
+            .map(|line| (Hand::parse(line, rules), &line[..rules.hand_size]))
+            .collect();
+
+        hands.sort_unstable();
+
+        let rows = hands
+            .into_iter()
+            .enumerate()
+            .map(|(index, (hand, cards))| ReportRow {
+                rank: index + 1,
+                hand: String::from_utf8_lossy(cards).to_string(),
+                hand_type: rules.hand_type(cards),
+                best_type: rules.best_hand_type(cards),
+                bid: hand.bid,
+                winnings: (index + 1) * hand.bid,
+            })
+            .collect();
+
+        Self { rows }
+    }
+
+    fn total(&self) -> usize {
+        self.rows.iter().map(|row| row.winnings).sum()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let hand_width = self
+            .rows
+            .iter()
+            .map(|row| row.hand.len())
+            .max()
+            .unwrap_or(0)
+            .max(4);
+
+        writeln!(
+            f,
+            "{:>5} | {:<hand_width$} | {:<15} | {:<15} | {:>10} | {:>12}",
+            "Rank", "Hand", "Type", "Upgraded type", "Bid", "Winnings"
+        )?;
+        for row in self.rows.iter() {
+            writeln!(
+                f,
+                "{:>5} | {:<hand_width$} | {:<15} | {:<15} | {:>10} | {:>12}",
+                row.rank,
+                row.hand,
+                type_name(&row.hand_type),
+                type_name(&row.best_type),
+                row.bid,
+                row.winnings
+            )?;
+        }
+        write!(f, "Total winnings: {}", self.total())
+    }
+}
+
+fn total_winnings(input: &[u8], rules: &RuleSet) -> usize {
+    let mut hands: Vec<Hand> = input
         .split(|c| *c == b'\n')
-        .map(|line| {
-            let entry = 0_u64;
-            let hand = line.iter().take(5).enumerate().fold(entry, |acc, (i, c)| {
-                let bits: u64 = match c {
-                    b'2'..=b'9' => (c - b'1') as u64,
-                    b'T' => 9,
-                    b'J' => 0,
-                    b'Q' => 10,
-                    b'K' => 11,
-                    b'A' => 12,
-                    _ => panic!(),
-                } << (64 - 4 * (i + 2));
-                acc | bits
-            });
-            let mut hand_buf = [0; 5];
-            hand_buf.copy_from_slice(&line[0..=4]);
-            let value = (get_value_with_joker(&mut hand_buf) as u64) << 60;
-            let bid = line
-                .iter()
-                .skip(6)
-                .fold(0, |acc, digit| 10 * acc + (digit - b'0') as u64);
-            value | hand | bid
-        })
+        .map(|line| Hand::parse(line, rules))
         .collect();
 
-    hands.sort();
+    hands.sort_unstable();
 
     hands
         .iter()
         .enumerate()
-        .map(|(index, bid)| (index + 1) * (bid & 0xfff) as usize)
+        .map(|(index, hand)| (index + 1) * hand.bid)
         .sum()
 }
 
+fn part1(input: &[u8]) -> usize {
+    total_winnings(input, &RuleSet::standard())
+}
+
+fn part2(input: &[u8]) -> usize {
+    total_winnings(input, &RuleSet::with_joker())
+}
+
 pub fn main() {
     let input = INPUT.trim_ascii_end();
 
@@ -146,6 +322,14 @@ pub fn main() {
     println!("{}", part2(input));
 }
 
+pub fn explain() {
+    let input = INPUT.trim_ascii_end();
+
+    println!("{}", Report::explain(input, &RuleSet::standard()));
+    println!();
+    println!("{}", Report::explain(input, &RuleSet::with_joker()));
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -165,6 +349,118 @@ mod tests {
         assert_eq!(part2(input), 5905);
     }
 
+    #[test]
+    fn test_hand_types() {
+        let rules = RuleSet::standard();
+        assert_eq!(rules.hand_types.len(), 7);
+        assert_eq!(rules.type_rank(&rules.hand_type(b"23456")), 0);
+        assert_eq!(rules.type_rank(&rules.hand_type(b"A23A4")), 1);
+        assert_eq!(rules.type_rank(&rules.hand_type(b"23432")), 2);
+        assert_eq!(rules.type_rank(&rules.hand_type(b"TTT98")), 3);
+        assert_eq!(rules.type_rank(&rules.hand_type(b"23332")), 4);
+        assert_eq!(rules.type_rank(&rules.hand_type(b"AA8AA")), 5);
+        assert_eq!(rules.type_rank(&rules.hand_type(b"AAAAA")), 6);
+        assert_eq!(rules.best_hand_type(b"KTJJT"), vec![2, 2, 1]);
+    }
+
+    #[test]
+    fn test_joker_upgrades() {
+        let rules = RuleSet::with_joker();
+        assert_eq!(rules.best_hand_type(b"KTJJT"), vec![4, 1]);
+        assert_eq!(rules.best_hand_type(b"JJJJJ"), vec![5]);
+        assert_eq!(rules.best_hand_type(b"2345J"), vec![2, 1, 1, 1]);
+        assert_eq!(rules.best_hand_type(b"2244J"), vec![3, 2]);
+        assert_eq!(rules.best_hand_type(b"224JJ"), vec![4, 1]);
+        assert_eq!(rules.hand_type(b"224JJ"), vec![2, 2, 1]);
+
+        let rules = RuleSet::new(b"J23456789TQKA", b"J2", 5);
+        assert_eq!(rules.best_hand_type(b"2345J"), vec![3, 1, 1]);
+        assert_eq!(rules.best_hand_type(b"22J34"), vec![4, 1]);
+    }
+
+    #[test]
+    fn test_custom_hand_size() {
+        let rules = RuleSet::new(b"123", b"", 3);
+        assert_eq!(rules.hand_types, vec![vec![1, 1, 1], vec![2, 1], vec![3]]);
+
+        let input = b"123 1\n111 10\n311 100\n113 1000";
+        assert_eq!(
+            total_winnings(input, &rules),
+            1 + 1000 * 2 + 100 * 3 + 10 * 4
+        );
+
+        let rules = RuleSet::new(b"x123", b"x", 3);
+        let input = b"12x 1\n11x 10\n312 100";
+        assert_eq!(total_winnings(input, &rules), 100 + 2 + 10 * 3);
+    }
+
+    #[test]
+    fn test_custom_hand_types() {
+        let mut hand_types = RuleSet::standard().hand_types;
+        hand_types.reverse();
+        let rules = RuleSet::with_hand_types(b"23456789TJQKA", b"", 5, hand_types);
+        assert_eq!(rules.type_rank(&[1, 1, 1, 1, 1]), 6);
+        assert_eq!(rules.type_rank(&[5]), 0);
+
+        let input = b"AAAAA 1\n23456 10\n22345 100";
+        assert_eq!(total_winnings(input, &rules), 1 + 100 * 2 + 10 * 3);
+
+        let mut hand_types = RuleSet::with_joker().hand_types;
+        hand_types.swap(5, 6);
+        let rules = RuleSet::with_hand_types(b"J23456789TQKA", b"J", 5, hand_types);
+        assert_eq!(rules.best_hand_type(b"2222J"), vec![4, 1]);
+        assert_eq!(rules.best_hand_type(b"JJJJJ"), vec![4, 1]);
+        assert_eq!(rules.best_hand_type(b"2233J"), vec![3, 2]);
+    }
+
+    #[test]
+    fn test_report() {
+        let input = TEST_INPUT.trim_ascii_end();
+        let report = Report::explain(input, &RuleSet::with_joker());
+        assert_eq!(report.total(), 5905);
+        assert_eq!(
+            report.rows[4],
+            ReportRow {
+                rank: 5,
+                hand: "KTJJT".to_string(),
+                hand_type: vec![2, 2, 1],
+                best_type: vec![4, 1],
+                bid: 220,
+                winnings: 1100,
+            }
+        );
+        assert_eq!(
+            report.to_string(),
+            [
+                " Rank | Hand  | Type            | Upgraded type   |        Bid |     Winnings",
+                "    1 | 32T3K | One pair        | One pair        |        765 |          765",
+                "    2 | KK677 | Two pair        | Two pair        |         28 |           56",
+                "    3 | T55J5 | Three of a kind | Four of a kind  |        684 |         2052",
+                "    4 | QQQJA | Three of a kind | Four of a kind  |        483 |         1932",
+                "    5 | KTJJT | Two pair        | Four of a kind  |        220 |         1100",
+                "Total winnings: 5905",
+            ]
+            .join("\n")
+        );
+
+        let report = Report::explain(b"123 1\n111 10", &RuleSet::new(b"123", b"", 3));
+        assert_eq!(type_name(&report.rows[1].best_type), "3");
+        assert_eq!(type_name(&report.rows[0].hand_type), "1+1+1");
+    }
+
+    #[test]
+    fn test_large_bids() {
+        let input = b"32T3K 765000\nT55J5 4096\nKK677 28000000000\nKTJJT 220\nQQQJA 4095";
+        assert_eq!(
+            part1(input),
+            765000 + 220 * 2 + 28000000000 * 3 + 4096 * 4 + 4095 * 5
+        );
+        assert_eq!(
+            part2(input),
+            765000 + 28000000000 * 2 + 4096 * 3 + 4095 * 4 + 220 * 5
+        );
+    }
+
     #[bench]
     fn bench_part1(b: &mut Bencher) {
         let input = INPUT.trim_ascii_end();
@@ -1,5 +1,8 @@
 extern crate test;
 
+use num::Integer;
+use std::collections::HashMap;
+
 const INPUT: &[u8] = include_bytes!("../inputs/day08.txt");
 
 fn parse_instructions(line: &[u8]) -> Vec<u8> {
@@ -12,83 +15,235 @@ fn parse_instructions(line: &[u8]) -> Vec<u8> {
         .collect()
 }
 
-fn get_node_number(node: &[u8]) -> usize {
-    node.iter().fold(0, |acc, c| acc * 26 + (c - b'A') as usize)
+#[derive(Debug, PartialEq, Eq)]
+enum NavigationError {
+    UnknownNode(String),
+    Unreachable(String, String),
 }
 
-fn is_start_node(node: usize) -> bool {
-    (node % (26 * 26)) % 26 == 0
+struct Network<'a> {
+    instructions: Vec<u8>,
+    labels: Vec<&'a [u8]>,
+    map: Vec<[usize; 2]>,
 }
 
-fn is_end_node(node: usize) -> bool {
-    (node % (26 * 26)) % 26 == 25
-}
+impl<'a> Network<'a> {
+    fn parse(input: &'a [u8]) -> Self {
+        let mut lines = input.split(|c| *c == b'\n');
+        let instructions: Vec<u8> = parse_instructions(lines.next().unwrap());
 
-fn part1(input: &[u8]) -> usize {
-    let mut lines = input.split(|c| *c == b'\n');
-    let instructions: Vec<u8> = parse_instructions(lines.next().unwrap());
+        let entries: Vec<(&[u8], &[u8], &[u8])> = lines
+            .skip(1)
+            .map(|line| {
+                let mut groups = line
+                    .split(|c| matches!(c, b' ' | b'=' | b'(' | b',' | b')'))
+                    .filter(|group| !group.is_empty());
+                let node = groups.next().unwrap();
+                let left = groups.next().unwrap();
+                let right = groups.next().unwrap();
+                (node, left, right)
+            })
+            .collect();
+
+        let labels: Vec<&[u8]> = entries.iter().map(|(node, _, _)| *node).collect();
+        let indices: HashMap<&[u8], usize> = labels
+            .iter()
+            .enumerate()
+            .map(|(index, label)| (*label, index))
+            .collect();
+        let map = entries
+            .iter()
+            .map(|(_, left, right)| [indices[left], indices[right]])
+            .collect();
 
-    let mut map = [[0_usize; 2]; 26 * 26 * 26];
+        Self {
+            instructions,
+            labels,
+            map,
+        }
+    }
 
-    lines.skip(1).for_each(|line| {
-        let node = get_node_number(&line[0..=2]);
-        let left = get_node_number(&line[7..=9]);
-        let right = get_node_number(&line[12..=14]);
+    fn node(&self, label: &[u8]) -> Option<usize> {
+        self.labels.iter().position(|l| *l == label)
+    }
 
-        map[node] = [left, right];
-    });
+    fn nodes(&self, predicate: impl Fn(&[u8]) -> bool) -> Vec<bool> {
+        self.labels.iter().map(|label| predicate(label)).collect()
+    }
 
-    let mut node = 0;
-    let mut count = 0;
-    let target_node = get_node_number(b"ZZZ");
+    fn path(&self, from: &[u8], to: &[u8]) -> Result<Vec<&[u8]>, NavigationError> {
+        let unknown =
+            |label: &[u8]| NavigationError::UnknownNode(String::from_utf8_lossy(label).to_string());
+        let start = self.node(from).ok_or_else(|| unknown(from))?;
+        let target = self.node(to).ok_or_else(|| unknown(to))?;
 
-    for direction in instructions.iter().cycle() {
-        count += 1;
-        node = map[node][*direction as usize];
-        if node == target_node {
-            return count;
+        let num_instructions = self.instructions.len();
+        let mut seen = vec![false; self.map.len() * num_instructions];
+        let mut path = vec![start];
+        let mut node = start;
+        let mut step = 0;
+
+        while node != target {
+            let index = step % num_instructions;
+            let state = node * num_instructions + index;
+            if seen[state] {
+                return Err(NavigationError::Unreachable(
+                    String::from_utf8_lossy(from).to_string(),
+                    String::from_utf8_lossy(to).to_string(),
+                ));
+            }
+            seen[state] = true;
+            node = self.map[node][self.instructions[index] as usize];
+            path.push(node);
+            step += 1;
         }
-    }
 
-    panic!()
+        Ok(path.into_iter().map(|node| self.labels[node]).collect())
+    }
 }
 
-fn part2(input: &[u8]) -> usize {
-    let mut lines = input.split(|c| *c == b'\n');
-    let instructions: Vec<u8> = parse_instructions(lines.next().unwrap());
+fn part1(input: &[u8]) -> usize {
+    Network::parse(input).path(b"AAA", b"ZZZ").unwrap().len() - 1
+}
 
-    let mut map = [[0_usize; 2]; 26 * 26 * 26];
-    let mut nodes: Vec<usize> = vec![];
+struct GhostCycle {
+    offset: usize,
+    length: usize,
+    pre_hits: Vec<usize>,
+    cycle_hits: Vec<usize>,
+}
 
-    lines.skip(1).for_each(|line| {
-        let node = get_node_number(&line[0..=2]);
-        let left = get_node_number(&line[7..=9]);
-        let right = get_node_number(&line[12..=14]);
+impl GhostCycle {
+    fn detect(network: &Network, start: usize, is_end: &[bool]) -> Self {
+        let num_instructions = network.instructions.len();
+        let mut seen: Vec<usize> = vec![usize::MAX; network.map.len() * num_instructions];
+        let mut hits: Vec<usize> = vec![];
+        let mut node = start;
+        let mut step = 0;
 
-        map[node] = [left, right];
+        loop {
+            let index = step % num_instructions;
+            let state = node * num_instructions + index;
+            if seen[state] != usize::MAX {
+                let offset = seen[state];
+                let (pre_hits, cycle_hits) = hits.iter().partition(|hit| **hit < offset);
+                return Self {
+                    offset,
+                    length: step - offset,
+                    pre_hits,
+                    cycle_hits,
+                };
+            }
+            seen[state] = step;
+            if is_end[node] {
+                hits.push(step);
+            }
+            node = network.map[node][network.instructions[index] as usize];
+            step += 1;
+        }
+    }
 
-        if is_start_node(node) {
-            nodes.push(node);
+    fn is_hit(&self, step: usize) -> bool {
+        if step < self.offset {
+            self.pre_hits.contains(&step)
+        } else {
+            let step = self.offset + (step - self.offset) % self.length;
+            self.cycle_hits.contains(&step)
         }
-    });
+    }
+}
 
-    nodes
+fn combine_residues(a: (i128, i128), b: (i128, i128)) -> Option<Option<(i128, i128)>> {
+    let (r1, m1) = a;
+    let (r2, m2) = b;
+    let egcd = m1.extended_gcd(&m2);
+    let g = egcd.gcd;
+    let diff = r2.checked_sub(r1)?;
+    if diff % g != 0 {
+        return Some(None);
+    }
+    let lcm = (m1 / g).checked_mul(m2)?;
+    let t = (diff / g).checked_mul(egcd.x)?.rem_euclid(m2 / g);
+    let r = m1.checked_mul(t)?.checked_add(r1)?.rem_euclid(lcm);
+    Some(Some((r, lcm)))
+}
+
+fn first_common_hit(cycles: &[GhostCycle]) -> Option<usize> {
+    let max_offset = cycles.iter().map(|cycle| cycle.offset).max()?;
+
+    let latest = cycles
         .iter()
-        .map(|start_node| {
-            let mut count = 0_usize;
-            let mut node = *start_node;
-
-            for direction in instructions.iter().cycle() {
-                count += 1;
-                node = map[node][*direction as usize];
-                if is_end_node(node) {
-                    break;
+        .find(|cycle| cycle.offset == max_offset)
+        .unwrap();
+    if let Some(step) = latest
+        .pre_hits
+        .iter()
+        .find(|step| cycles.iter().all(|cycle| cycle.is_hit(**step)))
+    {
+        return Some(*step);
+    }
+
+    let residues = cycles
+        .iter()
+        .try_fold(vec![(0_i128, 1_i128)], |residues, cycle| {
+            let mut combined = vec![];
+            for residue in residues.iter() {
+                for hit in cycle.cycle_hits.iter() {
+                    if let Some(residue) =
+                        combine_residues(*residue, (*hit as i128, cycle.length as i128))?
+                    {
+                        combined.push(residue);
+                    }
                 }
             }
+            combined.sort_unstable();
+            combined.dedup();
+            Some(combined)
+        })?;
 
-            count
+    let max_offset = max_offset as i128;
+    residues
+        .iter()
+        .map(|(r, m)| {
+            if *r >= max_offset {
+                Some(*r)
+            } else {
+                ((max_offset - r + m - 1) / m)
+                    .checked_mul(*m)?
+                    .checked_add(*r)
+            }
         })
-        .fold(1, num::integer::lcm)
+        .collect::<Option<Vec<i128>>>()?
+        .into_iter()
+        .min()
+        .and_then(|step| usize::try_from(step).ok())
+}
+
+fn ghost_steps(
+    network: &Network,
+    is_start: impl Fn(&[u8]) -> bool,
+    is_end: impl Fn(&[u8]) -> bool,
+) -> Option<usize> {
+    let is_end = network.nodes(is_end);
+
+    let cycles: Vec<GhostCycle> = network
+        .nodes(is_start)
+        .iter()
+        .enumerate()
+        .filter(|(_, is_start)| **is_start)
+        .map(|(node, _)| GhostCycle::detect(network, node, &is_end))
+        .collect();
+
+    first_common_hit(&cycles)
+}
+
+fn part2(input: &[u8]) -> usize {
+    ghost_steps(
+        &Network::parse(input),
+        |label| label.ends_with(b"A"),
+        |label| label.ends_with(b"Z"),
+    )
+    .unwrap()
 }
 
 pub fn main() {
@@ -118,16 +273,125 @@ mod tests {
     }
 
     #[test]
-    fn test_is_start_node() {
-        assert_eq!(is_end_node(get_node_number(b"BBA")), false);
-        assert_eq!(is_end_node(get_node_number(b"BBZ")), true);
+    fn test_path() {
+        let input = TEST_INPUT.trim_ascii_end();
+        let network = Network::parse(input);
+        assert_eq!(
+            network.path(b"AAA", b"ZZZ"),
+            Ok(vec![
+                &b"AAA"[..],
+                &b"BBB"[..],
+                &b"AAA"[..],
+                &b"BBB"[..],
+                &b"AAA"[..],
+                &b"BBB"[..],
+                &b"ZZZ"[..]
+            ])
+        );
+        assert_eq!(network.path(b"BBB", b"BBB"), Ok(vec![&b"BBB"[..]]));
+        assert_eq!(
+            network.path(b"ZZZ", b"AAA"),
+            Err(NavigationError::Unreachable(
+                "ZZZ".to_string(),
+                "AAA".to_string()
+            ))
+        );
+        assert_eq!(
+            network.path(b"AAA", b"YYY"),
+            Err(NavigationError::UnknownNode("YYY".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_part2_ghosts() {
+        let input = b"LR\n\n11A = (11B, XXX)\n11B = (XXX, 11Z)\n11Z = (11B, XXX)\n22A = (22B, XXX)\n22B = (22C, 22C)\n22C = (22Z, 22Z)\n22Z = (22B, 22B)\nXXX = (XXX, XXX)";
+        assert_eq!(part2(input), 6);
+    }
+
+    #[test]
+    fn test_ghost_cycles() {
+        let input = b"L\n\nAAA = (BBZ, BBZ)\nBBZ = (CCC, CCC)\nCCC = (DDZ, DDZ)\nDDZ = (EEE, EEE)\nEEE = (FFF, FFF)\nFFF = (CCC, CCC)\nGGA = (HHZ, HHZ)\nHHZ = (HHH, HHH)\nHHH = (HHZ, HHZ)";
+        // AAA hits Z at steps 1, 3, 7, 11, ...; GGA hits Z at every odd step.
+        assert_eq!(part2(input), 1);
+
+        let input = b"L\n\nAAA = (BBB, BBB)\nBBB = (CCZ, CCZ)\nCCZ = (BBB, BBB)\nDDA = (EEE, EEE)\nEEE = (FFF, FFF)\nFFF = (GGZ, GGZ)\nGGZ = (EEE, EEE)";
+        // AAA hits Z at steps 2, 4, 6, ...; DDA hits Z at steps 3, 6, 9, ...
+        assert_eq!(part2(input), 6);
+
+        let input = b"L\n\nAAA = (BBZ, BBZ)\nBBZ = (BBZ, BBZ)\nCCA = (DDD, DDD)\nDDD = (EEZ, EEZ)\nEEZ = (DDD, DDD)";
+        // AAA stays on BBZ from step 1, CCA hits Z at steps 2, 4, ...
+        assert_eq!(part2(input), 2);
+
+        let input = b"L\n\nAAA = (BBB, BBB)\nBBB = (CCZ, CCZ)\nCCZ = (BBB, BBB)\nDDA = (FFZ, FFZ)\nFFZ = (GGG, GGG)\nGGG = (FFZ, FFZ)";
+        // AAA hits Z at even steps, DDA at odd steps.
+        let network = Network::parse(input);
+        let is_end = network.nodes(|label| label.ends_with(b"Z"));
+        let cycles: Vec<GhostCycle> = [b"AAA", b"DDA"]
+            .iter()
+            .map(|label| GhostCycle::detect(&network, network.node(*label).unwrap(), &is_end))
+            .collect();
+        assert_eq!((cycles[0].offset, cycles[0].length), (1, 2));
+        assert_eq!(cycles[0].cycle_hits, vec![2]);
+        assert_eq!((cycles[1].offset, cycles[1].length), (1, 2));
+        assert_eq!(cycles[1].cycle_hits, vec![1]);
+        assert_eq!(first_common_hit(&cycles), None);
+    }
+
+    #[test]
+    fn test_combine_residues() {
+        assert_eq!(combine_residues((1, 4), (3, 6)), Some(Some((9, 12))));
+        assert_eq!(combine_residues((0, 4), (1, 6)), Some(None));
+        assert_eq!(combine_residues((0, i128::MAX), (1, i128::MAX - 1)), None);
+
+        let cycles: Vec<GhostCycle> = (0..10)
+            .map(|_| GhostCycle {
+                offset: 3,
+                length: 12,
+                pre_hits: vec![],
+                cycle_hits: (3..15).collect(),
+            })
+            .collect();
+        assert_eq!(first_common_hit(&cycles), Some(3));
+
+        let cycles: Vec<GhostCycle> = [i64::MAX as usize, i64::MAX as usize - 1]
+            .iter()
+            .map(|length| GhostCycle {
+                offset: 0,
+                length: *length,
+                pre_hits: vec![],
+                cycle_hits: vec![length - 1],
+            })
+            .collect();
+        assert_eq!(first_common_hit(&cycles), None);
     }
 
     #[test]
-    fn test_is_end_node() {
-        assert_eq!(is_end_node(get_node_number(b"BBA")), false);
-        assert_eq!(is_end_node(get_node_number(b"BBZ")), true);
-        assert_eq!(is_end_node(get_node_number(b"ZZZ")), true);
+    fn test_arbitrary_labels() {
+        let input = b"RL\n\nstart = (left1, exit)\nleft1 = (start, start)\nexit = (exit, exit)\nx = (start, x)";
+        let network = Network::parse(input);
+        assert_eq!(network.labels.len(), 4);
+        assert_eq!(network.node(b"exit"), Some(2));
+        assert_eq!(network.map[network.node(b"start").unwrap()], [1, 2]);
+        assert_eq!(
+            ghost_steps(
+                &network,
+                |label| label == b"start",
+                |label| label == b"exit"
+            ),
+            Some(1)
+        );
+        assert_eq!(
+            ghost_steps(
+                &network,
+                |label| label.len() <= 5,
+                |label| label.starts_with(b"l")
+            ),
+            None
+        );
+        assert_eq!(
+            ghost_steps(&network, |label| label == b"x", |label| label == b"exit"),
+            Some(3)
+        );
     }
 
     #[bench]
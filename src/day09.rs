@@ -1,55 +1,188 @@
 extern crate test;
 
+use num::rational::Ratio;
+
 const INPUT: &[u8] = include_bytes!("../inputs/day09.txt");
 
-fn parse_i64(num: &[u8]) -> i64 {
-    num.iter()
-        .fold(0, |acc, digit| acc * 10 + (digit & 0x0f) as i64)
+#[derive(Debug, PartialEq, Eq)]
+enum OasisError {
+    Overflow,
+    NotPolynomial,
 }
 
-fn solution(input: &[u8], reversed: bool) -> i64 {
-    input
-        .split(|c| *c == b'\n')
-        .map(|line| {
-            let mut nums: Vec<i64> = line
-                .split(|c| *c == b' ')
-                .map(|num| {
-                    if num[0] == b'-' {
-                        -parse_i64(&num[1..])
-                    } else {
-                        parse_i64(num)
-                    }
-                })
-                .collect();
-            if reversed {
-                nums.reverse();
+fn parse_i64(num: &[u8]) -> Result<i64, OasisError> {
+    let (sign, digits) = match num.split_first() {
+        Some((b'-', digits)) => (-1, digits),
+        _ => (1, num),
+    };
+    digits
+        .iter()
+        .try_fold(0_i64, |acc, digit| {
+            acc.checked_mul(10)?
+                .checked_add(sign * (digit & 0x0f) as i64)
+        })
+        .ok_or(OasisError::Overflow)
+}
+
+fn parse_line(line: &[u8]) -> Result<Vec<i64>, OasisError> {
+    line.split(|c| *c == b' ').map(parse_i64).collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Sequence {
+    len: usize,
+    differences: Vec<i64>,
+    exact: bool,
+}
+
+impl Sequence {
+    fn fit(values: &[i64]) -> Result<Self, OasisError> {
+        let mut row = values.to_vec();
+        let mut differences = vec![];
+
+        while row.iter().any(|value| *value != 0) {
+            differences.push(row[0]);
+            row = row
+                .windows(2)
+                .map(|pair| pair[1].checked_sub(pair[0]).ok_or(OasisError::Overflow))
+                .collect::<Result<Vec<i64>, OasisError>>()?;
+        }
+
+        Ok(Self {
+            len: values.len(),
+            differences,
+            exact: !row.is_empty(),
+        })
+    }
+
+    fn fit_strict(values: &[i64]) -> Result<Self, OasisError> {
+        let sequence = Self::fit(values)?;
+        if sequence.exact {
+            Ok(sequence)
+        } else {
+            Err(OasisError::NotPolynomial)
+        }
+    }
+
+    fn degree(&self) -> Option<usize> {
+        self.differences.len().checked_sub(1)
+    }
+
+    fn value_at(&self, index: i64) -> Result<i64, OasisError> {
+        let index = index as i128;
+        let mut binomial: i128 = 1;
+        let mut result: i128 = 0;
+
+        for (k, difference) in self.differences.iter().enumerate() {
+            if k > 0 {
+                binomial = binomial
+                    .checked_mul(index - k as i128 + 1)
+                    .ok_or(OasisError::Overflow)?
+                    / k as i128;
             }
+            result = binomial
+                .checked_mul(*difference as i128)
+                .and_then(|term| result.checked_add(term))
+                .ok_or(OasisError::Overflow)?;
+        }
 
-            let mut result = 0;
+        result.try_into().map_err(|_| OasisError::Overflow)
+    }
 
-            for i in (0..=nums.len() - 1).rev() {
-                let mut all_zero = true;
-                for j in 0..i {
-                    nums[j] = nums[j + 1] - nums[j];
-                    all_zero &= nums[j] == 0;
-                }
-                result += nums[i];
-                if all_zero {
-                    break;
+    fn extrapolate(&self, count: usize) -> Result<Vec<i64>, OasisError> {
+        (self.len..self.len + count)
+            .map(|index| self.value_at(index as i64))
+            .collect()
+    }
+
+    fn coefficients(&self) -> Result<Vec<Ratio<i128>>, OasisError> {
+        let n = self.differences.len();
+        let mut numerators: Vec<i128> = vec![0; n];
+        let mut falling: Vec<i128> = vec![1];
+        let mut factorial: i128 = 1;
+
+        for (k, difference) in self.differences.iter().enumerate() {
+            if k > 0 {
+                let shift = k as i128 - 1;
+                let mut next: Vec<i128> = vec![0; falling.len() + 1];
+                for (j, coefficient) in falling.iter().enumerate() {
+                    next[j + 1] = next[j + 1]
+                        .checked_add(*coefficient)
+                        .ok_or(OasisError::Overflow)?;
+                    next[j] = shift
+                        .checked_mul(*coefficient)
+                        .and_then(|term| next[j].checked_sub(term))
+                        .ok_or(OasisError::Overflow)?;
                 }
+                falling = next;
+            }
+            for (j, coefficient) in falling.iter().enumerate() {
+                numerators[j] = coefficient
+                    .checked_mul(*difference as i128)
+                    .and_then(|term| term.checked_mul(self.factorial_ratio(k)?))
+                    .and_then(|term| numerators[j].checked_add(term))
+                    .ok_or(OasisError::Overflow)?;
             }
+            if k > 0 {
+                factorial = factorial
+                    .checked_mul(k as i128)
+                    .ok_or(OasisError::Overflow)?;
+            }
+        }
+
+        Ok(numerators
+            .into_iter()
+            .map(|numerator| Ratio::new(numerator, factorial))
+            .collect())
+    }
 
-            result
+    fn factorial_ratio(&self, k: usize) -> Option<i128> {
+        (k + 1..self.differences.len()).try_fold(1_i128, |acc, i| acc.checked_mul(i as i128))
+    }
+}
+
+fn solution(
+    input: &[u8],
+    index: fn(usize) -> i64,
+    strict: bool,
+) -> Result<i64, (usize, OasisError)> {
+    input
+        .split(|c| *c == b'\n')
+        .enumerate()
+        .try_fold(0_i64, |total, (line_number, line)| {
+            parse_line(line)
+                .and_then(|nums| {
+                    let sequence = if strict {
+                        Sequence::fit_strict(&nums)
+                    } else {
+                        Sequence::fit(&nums)
+                    };
+                    sequence?.value_at(index(nums.len()))
+                })
+                .and_then(|value| total.checked_add(value).ok_or(OasisError::Overflow))
+                .map_err(|err| (line_number + 1, err))
         })
-        .sum()
+}
+
+fn non_polynomial_lines(input: &[u8]) -> Vec<usize> {
+    input
+        .split(|c| *c == b'\n')
+        .enumerate()
+        .filter(|(_, line)| {
+            parse_line(line)
+                .and_then(|nums| Sequence::fit(&nums))
+                .is_ok_and(|sequence| !sequence.exact)
+        })
+        .map(|(line_number, _)| line_number + 1)
+        .collect()
 }
 
 fn part1(input: &[u8]) -> i64 {
-    solution(input, false)
+    solution(input, |len| len as i64, false).unwrap()
 }
 
 fn part2(input: &[u8]) -> i64 {
-    solution(input, true)
+    solution(input, |_| -1, false).unwrap()
 }
 
 pub fn main() {
@@ -59,6 +192,45 @@ pub fn main() {
     println!("{}", part2(input));
 }
 
+pub fn strict() {
+    let input = INPUT.trim_ascii_end();
+
+    for index in [|len| len as i64, |_| -1] {
+        match solution(input, index, true) {
+            Ok(total) => println!("{}", total),
+            Err((line_number, err)) => println!("Line {}: {:?}", line_number, err),
+        }
+    }
+
+    for line_number in non_polynomial_lines(input) {
+        println!("Line {}: not exactly polynomial", line_number);
+    }
+}
+
+pub fn explain() {
+    let input = INPUT.trim_ascii_end();
+
+    for (line_number, line) in input.split(|c| *c == b'\n').enumerate() {
+        let sequence = parse_line(line).and_then(|nums| Sequence::fit(&nums));
+        match sequence {
+            Ok(sequence) => {
+                let coefficients: Vec<String> = match sequence.coefficients() {
+                    Ok(coefficients) => coefficients.iter().map(|c| c.to_string()).collect(),
+                    Err(err) => vec![format!("{:?}", err)],
+                };
+                println!(
+                    "Line {}: degree {:?}, coefficients [{}], next {:?}",
+                    line_number + 1,
+                    sequence.degree(),
+                    coefficients.join(", "),
+                    sequence.extrapolate(3)
+                );
+            }
+            Err(err) => println!("Line {}: {:?}", line_number + 1, err),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -78,6 +250,93 @@ mod tests {
         assert_eq!(part2(input), 2);
     }
 
+    #[test]
+    fn test_sequence() {
+        let sequence = Sequence::fit(&[10, 13, 16, 21, 30, 45]).unwrap();
+        assert_eq!(sequence.degree(), Some(3));
+        assert_eq!(sequence.value_at(6), Ok(68));
+        assert_eq!(sequence.value_at(-1), Ok(5));
+        assert_eq!(sequence.extrapolate(3), Ok(vec![68, 101, 146]));
+        assert_eq!(
+            sequence.coefficients(),
+            Ok(vec![
+                Ratio::new(10, 1),
+                Ratio::new(11, 3),
+                Ratio::new(-1, 1),
+                Ratio::new(1, 3)
+            ])
+        );
+        assert_eq!(sequence.value_at(1_000_000), Ok(333332333337000010));
+
+        let sequence = Sequence::fit(&[0, 0, 0]).unwrap();
+        assert_eq!(sequence.degree(), None);
+        assert_eq!(sequence.value_at(100), Ok(0));
+        assert_eq!(sequence.coefficients(), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_non_polynomial() {
+        let input = b"0 3 6 9 12 15\n1 2 4 8 16\n1 3 6 10 15 21\n5 -3";
+        assert_eq!(non_polynomial_lines(input), vec![2, 4]);
+        assert_eq!(
+            solution(input, |len| len as i64, false),
+            Ok(18 + 31 + 28 - 11)
+        );
+        assert_eq!(
+            solution(input, |len| len as i64, true),
+            Err((2, OasisError::NotPolynomial))
+        );
+
+        let input = TEST_INPUT.trim_ascii_end();
+        assert_eq!(non_polynomial_lines(input), vec![]);
+        assert_eq!(solution(input, |len| len as i64, true), Ok(114));
+
+        assert_eq!(Sequence::fit_strict(&[7]), Err(OasisError::NotPolynomial));
+        assert_eq!(Sequence::fit_strict(&[7, 7]).unwrap().degree(), Some(0));
+        assert!(Sequence::fit(&[1, 2, 4, 8, 16]).is_ok_and(|sequence| !sequence.exact));
+    }
+
+    #[test]
+    fn test_sequence_overflow() {
+        let sequence = Sequence::fit(&[0, 3, 6]).unwrap();
+        assert_eq!(sequence.degree(), Some(1));
+        assert_eq!(sequence.value_at(i64::MAX / 3), Ok(i64::MAX / 3 * 3));
+        assert_eq!(
+            sequence.value_at(i64::MAX / 3 + 1),
+            Err(OasisError::Overflow)
+        );
+
+        let sequence = Sequence::fit(&[0, 1, 4, 9]).unwrap();
+        assert_eq!(
+            sequence.value_at(3_000_000_000),
+            Ok(9_000_000_000_000_000_000)
+        );
+        assert_eq!(sequence.value_at(4_000_000_000), Err(OasisError::Overflow));
+
+        assert_eq!(
+            Sequence::fit(&[i64::MIN, i64::MAX]),
+            Err(OasisError::Overflow)
+        );
+
+        let line = b"0 2305843009213693952 4611686018427387904";
+        assert_eq!(
+            solution(line, |len| len as i64, true),
+            Ok(6917529027641081856)
+        );
+        assert_eq!(
+            solution(&[&line[..], &line[..]].join(&b'\n'), |len| len as i64, true),
+            Err((2, OasisError::Overflow))
+        );
+
+        assert_eq!(parse_i64(b"9223372036854775807"), Ok(i64::MAX));
+        assert_eq!(parse_i64(b"-9223372036854775808"), Ok(i64::MIN));
+        assert_eq!(parse_i64(b"9223372036854775808"), Err(OasisError::Overflow));
+        assert_eq!(
+            solution(b"1 2 3\n9223372036854775808 1", |len| len as i64, false),
+            Err((2, OasisError::Overflow))
+        );
+    }
+
     #[bench]
     fn bench_part1(b: &mut Bencher) {
         let input = INPUT.trim_ascii_end();
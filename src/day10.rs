@@ -2,172 +2,207 @@ extern crate test;
 
 const INPUT: &[u8] = include_bytes!("../inputs/day10.txt");
 
-fn get_start_type(p: &(usize, usize), grid: &[Vec<u8>]) -> u8 {
-    let (x, y) = *p;
-
-    let up = matches!(grid[y - 1][x], b'|' | b'7' | b'F');
-    let down = matches!(grid[y + 1][x], b'|' | b'L' | b'J');
-    let left = matches!(grid[y][x - 1], b'-' | b'L' | b'F');
-    let right = matches!(grid[y][x + 1], b'-' | b'7' | b'J');
-
-    match (up, down, left, right) {
-        (true, true, false, false) => b'|',
-        (false, false, true, true) => b'-',
-        (true, false, true, false) => b'J',
-        (true, false, false, true) => b'L',
-        (false, true, true, false) => b'7',
-        (false, true, false, true) => b'F',
-        _ => panic!(),
-    }
+#[derive(Debug, PartialEq, Eq)]
+enum MazeError {
+    MissingStart,
+    StartOnBorder(usize, usize),
+    NoLoop(usize, usize),
 }
 
-fn get_adjacent(p: &(usize, usize), grid: &[Vec<u8>]) -> ((usize, usize), (usize, usize)) {
+fn get_adjacent(p: &(usize, usize), grid: &[Vec<u8>]) -> Option<((usize, usize), (usize, usize))> {
     let (x, y) = *p;
     match grid[p.1][p.0] {
-        b'-' => ((x - 1, y), (x + 1, y)),
-        b'|' => ((x, y - 1), (x, y + 1)),
-        b'L' => ((x, y - 1), (x + 1, y)),
-        b'F' => ((x, y + 1), (x + 1, y)),
-        b'7' => ((x, y + 1), (x - 1, y)),
-        b'J' => ((x, y - 1), (x - 1, y)),
-        _ => panic!(),
+        b'-' => Some(((x - 1, y), (x + 1, y))),
+        b'|' => Some(((x, y - 1), (x, y + 1))),
+        b'L' => Some(((x, y - 1), (x + 1, y))),
+        b'F' => Some(((x, y + 1), (x + 1, y))),
+        b'7' => Some(((x, y + 1), (x - 1, y))),
+        b'J' => Some(((x, y - 1), (x - 1, y))),
+        _ => None,
     }
 }
 
-fn part1(input: &[u8]) -> u32 {
-    let num_cols = input.iter().take_while(|c| **c != b'\n').count() + 2;
-    let dummy_row: Vec<u8> = vec![b'.'; num_cols];
-
-    let mut start = (0, 0);
-
-    let mut grid: Vec<Vec<u8>> = std::iter::once(&dummy_row[..])
-        .chain(input.split(|c| *c == b'\n'))
-        .chain(std::iter::once(&dummy_row[..]))
-        .enumerate()
-        .map(|(y, line)| {
-            let line = std::iter::once(&b'.')
-                .chain(line.iter())
-                .chain(std::iter::once(&b'.'))
-                .enumerate()
-                .map(|(x, c)| {
-                    if *c == b'S' {
-                        start = (x, y);
-                    }
-                    *c
-                })
-                .collect();
-            line
-        })
-        .collect();
-
-    grid[start.1][start.0] = get_start_type(&start, &grid);
+fn trace_loop(start: &(usize, usize), grid: &[Vec<u8>]) -> Option<Vec<(usize, usize)>> {
+    let (_, last) = get_adjacent(start, grid)?;
 
-    let mut length = 0;
-    let mut p = start;
-    let mut prev = (start.0 - 1, start.1);
+    let mut path = vec![];
+    let mut p = *start;
+    let mut prev = last;
 
     loop {
-        let adj = get_adjacent(&p, &grid);
+        path.push(p);
 
-        if adj.0 == prev {
-            prev = p;
-            p = adj.1;
+        let adj = get_adjacent(&p, grid)?;
+
+        let next = if adj.0 == prev {
+            adj.1
+        } else if adj.1 == prev || p == *start {
+            adj.0
         } else {
-            prev = p;
-            p = adj.0;
+            return None;
+        };
+        prev = p;
+        p = next;
+
+        if p == *start {
+            return if prev == last { Some(path) } else { None };
         }
+    }
+}
 
-        length += 1;
+#[derive(Debug, Clone, Copy)]
+enum AreaMethod {
+    Scan,
+    Shoelace,
+}
 
-        if p == start {
-            return (length / 2) as u32;
+impl AreaMethod {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "scan" => Some(Self::Scan),
+            "shoelace" => Some(Self::Shoelace),
+            _ => None,
         }
     }
 }
 
-fn part2(input: &[u8]) -> u32 {
-    let num_cols = input.iter().take_while(|c| **c != b'\n').count() + 2;
-    let dummy_row: Vec<u8> = vec![b'.'; num_cols];
-
-    let mut start = (0, 0);
-
-    let mut grid: Vec<Vec<u8>> = std::iter::once(&dummy_row[..])
-        .chain(input.split(|c| *c == b'\n'))
-        .chain(std::iter::once(&dummy_row[..]))
-        .enumerate()
-        .map(|(y, line)| {
-            let line = std::iter::once(&b'.')
-                .chain(line.iter())
-                .chain(std::iter::once(&b'.'))
-                .enumerate()
-                .map(|(x, c)| {
-                    if *c == b'S' {
-                        start = (x, y);
-                    }
-                    *c
-                })
-                .collect();
-            line
-        })
-        .collect();
-
-    grid[start.1][start.0] = get_start_type(&start, &grid);
+struct PipeMaze {
+    grid: Vec<Vec<u8>>,
+    start_shape: u8,
+    path: Vec<(usize, usize)>,
+}
 
-    let mut loop_grid: Vec<Vec<u8>> = vec![vec![b'.'; grid[0].len()]; grid.len()];
+impl PipeMaze {
+    fn parse(input: &[u8]) -> Result<Self, MazeError> {
+        let num_cols = input.iter().take_while(|c| **c != b'\n').count() + 2;
+        let dummy_row: Vec<u8> = vec![b'.'; num_cols];
+
+        let mut start = None;
+
+        let mut grid: Vec<Vec<u8>> = std::iter::once(&dummy_row[..])
+            .chain(input.split(|c| *c == b'\n'))
+            .chain(std::iter::once(&dummy_row[..]))
+            .enumerate()
+            .map(|(y, line)| {
+                let line = std::iter::once(&b'.')
+                    .chain(line.iter())
+                    .chain(std::iter::once(&b'.'))
+                    .enumerate()
+                    .map(|(x, c)| {
+                        if *c == b'S' {
+                            start = Some((x, y));
+                        }
+                        *c
+                    })
+                    .collect();
+                line
+            })
+            .collect();
+
+        let start = start.ok_or(MazeError::MissingStart)?;
+
+        for start_shape in b"|-LJ7F".iter() {
+            grid[start.1][start.0] = *start_shape;
+            if let Some(path) = trace_loop(&start, &grid) {
+                return Ok(Self {
+                    grid,
+                    start_shape: *start_shape,
+                    path,
+                });
+            }
+        }
 
-    let mut p = start;
-    let mut prev = (start.0 - 1, start.1);
+        if start.0 == 1 || start.1 == 1 || start.0 + 2 == num_cols || start.1 + 2 == grid.len() {
+            Err(MazeError::StartOnBorder(start.0 - 1, start.1 - 1))
+        } else {
+            Err(MazeError::NoLoop(start.0 - 1, start.1 - 1))
+        }
+    }
 
-    loop {
-        loop_grid[p.1][p.0] = grid[p.1][p.0];
+    fn start_shape(&self) -> u8 {
+        self.start_shape
+    }
 
-        let adj = get_adjacent(&p, &grid);
+    fn loop_tiles(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.path.iter().map(|(x, y)| (x - 1, y - 1))
+    }
 
-        if adj.0 == prev {
-            prev = p;
-            p = adj.1;
-        } else {
-            prev = p;
-            p = adj.0;
-        }
+    fn farthest_distance(&self) -> usize {
+        self.path.len() / 2
+    }
 
-        if p == start {
-            break;
-        }
+    fn farthest_tile(&self) -> (usize, usize) {
+        let (x, y) = self.path[self.farthest_distance()];
+        (x - 1, y - 1)
     }
 
-    let mut result = 0;
+    fn enclosed_tiles(&self) -> Vec<(usize, usize)> {
+        let mut loop_grid: Vec<Vec<u8>> = vec![vec![b'.'; self.grid[0].len()]; self.grid.len()];
+        for (x, y) in self.path.iter() {
+            loop_grid[*y][*x] = self.grid[*y][*x];
+        }
 
-    for line in loop_grid {
-        let mut crossing_count: u8 = 0;
-        let mut entry = 0;
-        for c in line {
-            match c {
-                b'.' => {
-                    if crossing_count % 2 == 1 {
-                        result += 1;
+        let mut result = vec![];
+
+        for (y, line) in loop_grid.into_iter().enumerate() {
+            let mut crossing_count: u8 = 0;
+            let mut entry = 0;
+            for (x, c) in line.into_iter().enumerate() {
+                match c {
+                    b'.' => {
+                        if crossing_count % 2 == 1 {
+                            result.push((x - 1, y - 1));
+                        }
                     }
-                }
-                b'|' => {
-                    crossing_count += 1;
-                }
-                b'-' => {}
-                b'L' | b'F' => {
-                    entry = c;
-                }
-                b'7' | b'J' => match (entry, c) {
-                    (b'L', b'7') => crossing_count += 1,
-                    (b'F', b'J') => crossing_count += 1,
-                    (b'L', b'J') => {}
-                    (b'F', b'7') => {}
+                    b'|' => {
+                        crossing_count += 1;
+                    }
+                    b'-' => {}
+                    b'L' | b'F' => {
+                        entry = c;
+                    }
+                    b'7' | b'J' => match (entry, c) {
+                        (b'L', b'7') => crossing_count += 1,
+                        (b'F', b'J') => crossing_count += 1,
+                        (b'L', b'J') => {}
+                        (b'F', b'7') => {}
+                        _ => panic!(),
+                    },
                     _ => panic!(),
-                },
-                _ => panic!(),
+                }
             }
         }
+
+        result
     }
 
-    result
+    fn enclosed_count(&self, method: AreaMethod) -> usize {
+        match method {
+            AreaMethod::Scan => self.enclosed_tiles().len(),
+            AreaMethod::Shoelace => {
+                let twice_area = self
+                    .path
+                    .iter()
+                    .zip(self.path.iter().cycle().skip(1))
+                    .map(|((x1, y1), (x2, y2))| (x1 * y2) as isize - (x2 * y1) as isize)
+                    .sum::<isize>()
+                    .unsigned_abs();
+                (twice_area - self.path.len()) / 2 + 1
+            }
+        }
+    }
+}
+
+fn part1(input: &[u8]) -> u32 {
+    PipeMaze::parse(input).unwrap().farthest_distance() as u32
+}
+
+fn enclosed(input: &[u8], method: AreaMethod) -> u32 {
+    PipeMaze::parse(input).unwrap().enclosed_count(method) as u32
+}
+
+fn part2(input: &[u8]) -> u32 {
+    enclosed(input, AreaMethod::Scan)
 }
 
 pub fn main() {
@@ -177,9 +212,36 @@ pub fn main() {
     println!("{}", part2(input));
 }
 
+pub fn with_area_method(name: &str) {
+    let input = INPUT.trim_ascii_end();
+    let method = AreaMethod::parse(name).unwrap();
+
+    println!("{}", part1(input));
+    println!("{}", enclosed(input, method));
+}
+
+pub fn explain() {
+    let input = INPUT.trim_ascii_end();
+    let maze = PipeMaze::parse(input).unwrap();
+
+    println!("Start shape: {}", maze.start_shape() as char);
+    println!(
+        "Farthest tile: {:?} at distance {}",
+        maze.farthest_tile(),
+        maze.farthest_distance()
+    );
+    for (x, y) in maze.loop_tiles() {
+        println!("Loop tile: {},{}", x, y);
+    }
+    for (x, y) in maze.enclosed_tiles() {
+        println!("Enclosed tile: {},{}", x, y);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::XorShift;
     use test::Bencher;
 
     const TEST_INPUT_1: &[u8] = include_bytes!("../test_inputs/day10_1.txt");
@@ -197,6 +259,203 @@ mod tests {
         assert_eq!(part2(input), 10);
     }
 
+    #[test]
+    fn test_pipe_maze() {
+        let input = TEST_INPUT_1.trim_ascii_end();
+        let maze = PipeMaze::parse(input).unwrap();
+        assert_eq!(maze.start_shape(), b'F');
+        assert_eq!(
+            maze.loop_tiles().collect::<Vec<(usize, usize)>>(),
+            vec![
+                (0, 2),
+                (0, 3),
+                (0, 4),
+                (1, 4),
+                (1, 3),
+                (2, 3),
+                (3, 3),
+                (4, 3),
+                (4, 2),
+                (3, 2),
+                (3, 1),
+                (3, 0),
+                (2, 0),
+                (2, 1),
+                (1, 1),
+                (1, 2)
+            ]
+        );
+        assert_eq!(maze.farthest_tile(), (4, 2));
+        assert_eq!(maze.enclosed_tiles(), vec![(2, 2)]);
+
+        let input = b"...........\n.S-------7.\n.|F-----7|.\n.||.....||.\n.||.....||.\n.|L-7.F-J|.\n.|..|.|..|.\n.L--J.L--J.\n...........";
+        let maze = PipeMaze::parse(input).unwrap();
+        assert_eq!(maze.enclosed_tiles(), vec![(2, 6), (3, 6), (7, 6), (8, 6)]);
+    }
+
+    #[test]
+    fn test_ambiguous_start() {
+        let input = b".....\n.F-7.\n.|.|.\n.L-S-\n.....";
+        let maze = PipeMaze::parse(input).unwrap();
+        assert_eq!(maze.start_shape(), b'J');
+        assert_eq!(maze.farthest_distance(), 4);
+
+        let input = b".......\n.F-7...\n.|.|...\n.L-S-7.\n...|.|.\n...L-J.\n.......";
+        let maze = PipeMaze::parse(input).unwrap();
+        assert_eq!(maze.start_shape(), b'J');
+        assert_eq!(maze.farthest_tile(), (1, 1));
+    }
+
+    #[test]
+    fn test_broken_maze() {
+        assert_eq!(PipeMaze::parse(b"S-7\n|.|\nL-J").err(), None);
+        assert_eq!(
+            PipeMaze::parse(b"S-7\n..|\nL-J").err(),
+            Some(MazeError::StartOnBorder(0, 0))
+        );
+        assert_eq!(
+            PipeMaze::parse(b".....\n.F-7.\n.|.|.\n.L-S.\n.....").err(),
+            None
+        );
+        assert_eq!(
+            PipeMaze::parse(b".....\n.F-7.\n.|.|.\n.L.S.\n.....").err(),
+            Some(MazeError::NoLoop(3, 3))
+        );
+        assert_eq!(
+            PipeMaze::parse(b".....\n.F-7.\n.|.|.\n.L-J.\n.....").err(),
+            Some(MazeError::MissingStart)
+        );
+        assert_eq!(
+            PipeMaze::parse(b".....\n.F-7.\n.S.|.\n.|.|.\n.L.J.\n.....").err(),
+            Some(MazeError::NoLoop(1, 2))
+        );
+    }
+
+    #[test]
+    fn test_area_methods() {
+        for input in [TEST_INPUT_1, TEST_INPUT_2] {
+            let maze = PipeMaze::parse(input.trim_ascii_end()).unwrap();
+            assert_eq!(
+                maze.enclosed_count(AreaMethod::Scan),
+                maze.enclosed_count(AreaMethod::Shoelace)
+            );
+        }
+        let maze = PipeMaze::parse(TEST_INPUT_2.trim_ascii_end()).unwrap();
+        assert_eq!(maze.enclosed_count(AreaMethod::Shoelace), 10);
+    }
+
+    fn generate_loop(rng: &mut XorShift) -> Option<(Vec<u8>, usize)> {
+        let (w, h) = (1 + rng.next(6), 1 + rng.next(6));
+        let mut blob = vec![vec![false; w + 2]; h + 2];
+        let (mut x, mut y) = (1 + rng.next(w), 1 + rng.next(h));
+        blob[y][x] = true;
+        for _ in 0..rng.next(w * h * 2) {
+            match rng.next(4) {
+                0 if x > 1 => x -= 1,
+                1 if x < w => x += 1,
+                2 if y > 1 => y -= 1,
+                3 if y < h => y += 1,
+                _ => {}
+            }
+            blob[y][x] = true;
+        }
+
+        let mut outside = vec![vec![false; w + 2]; h + 2];
+        let mut stack = vec![(0, 0)];
+        while let Some((x, y)) = stack.pop() {
+            if blob[y][x] || outside[y][x] {
+                continue;
+            }
+            outside[y][x] = true;
+            if x > 0 {
+                stack.push((x - 1, y));
+            }
+            if x < w + 1 {
+                stack.push((x + 1, y));
+            }
+            if y > 0 {
+                stack.push((x, y - 1));
+            }
+            if y < h + 1 {
+                stack.push((x, y + 1));
+            }
+        }
+        for y in 0..=h {
+            for x in 0..=w {
+                if !blob[y][x] && !outside[y][x] {
+                    return None;
+                }
+                if blob[y][x] == blob[y + 1][x + 1]
+                    && blob[y + 1][x] == blob[y][x + 1]
+                    && blob[y][x] != blob[y][x + 1]
+                {
+                    return None;
+                }
+            }
+        }
+
+        let inside = |x: usize, y: usize| blob[y / 3][x / 3];
+        let (cols, rows) = (3 * (w + 2), 3 * (h + 2));
+        let on_loop = |x: usize, y: usize| {
+            inside(x, y) && (y - 1..=y + 1).any(|y| (x - 1..=x + 1).any(|x| !inside(x, y)))
+        };
+
+        let mut tiles = vec![];
+        let mut interior = 0;
+        let mut loop_tiles = vec![];
+        for y in 0..rows {
+            for x in 0..cols {
+                let tile = if y > 0 && x > 0 && y + 1 < rows && x + 1 < cols && on_loop(x, y) {
+                    let up = on_loop(x, y - 1);
+                    let down = on_loop(x, y + 1);
+                    let left = on_loop(x - 1, y);
+                    let right = on_loop(x + 1, y);
+                    loop_tiles.push(tiles.len());
+                    match (up, down, left, right) {
+                        (true, true, false, false) => b'|',
+                        (false, false, true, true) => b'-',
+                        (true, false, true, false) => b'J',
+                        (true, false, false, true) => b'L',
+                        (false, true, true, false) => b'7',
+                        (false, true, false, true) => b'F',
+                        _ => return None,
+                    }
+                } else {
+                    if inside(x, y) {
+                        interior += 1;
+                    }
+                    b".|-LJ7F"[rng.next(7)]
+                };
+                tiles.push(tile);
+            }
+        }
+
+        let start = loop_tiles[rng.next(loop_tiles.len())];
+        tiles[start] = b'S';
+
+        let input = tiles.chunks(cols).collect::<Vec<&[u8]>>().join(&b'\n');
+        let maze = PipeMaze::parse(&input).ok()?;
+        if maze.path.len() != loop_tiles.len() {
+            return None;
+        }
+
+        Some((input, interior))
+    }
+
+    #[test]
+    fn test_area_methods_generated() {
+        let mut rng = XorShift(0x2023_1210);
+        let mut generated = 0;
+        while generated < 200 {
+            if let Some((input, interior)) = generate_loop(&mut rng) {
+                let maze = PipeMaze::parse(&input).unwrap();
+                assert_eq!(maze.enclosed_count(AreaMethod::Scan), interior);
+                assert_eq!(maze.enclosed_count(AreaMethod::Shoelace), interior);
+                generated += 1;
+            }
+        }
+    }
+
     #[bench]
     fn bench_part1(b: &mut Bencher) {
         let input = INPUT.trim_ascii_end();
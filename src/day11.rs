@@ -1,72 +1,124 @@
 extern crate test;
 
-use std::collections::HashSet;
-
 const INPUT: &[u8] = include_bytes!("../inputs/day11.txt");
 
-fn solution(input: &[u8], scale: usize) -> usize {
-    let mut galaxies: Vec<(usize, usize)> = vec![];
-    let mut zero_rows: Vec<usize> = vec![];
-    let mut zero_cols: Vec<usize> = vec![];
-    let mut occupied_cols: HashSet<usize> = HashSet::new();
-
+fn parse_galaxies(input: &[u8]) -> Vec<(usize, usize)> {
     input
         .split(|c| *c == b'\n')
         .enumerate()
-        .for_each(|(y, line)| {
-            let mut line_empty = true;
-
-            line.iter().enumerate().for_each(|(x, c)| {
-                if *c == b'#' {
-                    galaxies.push((x, y));
-                    occupied_cols.insert(x);
-                    line_empty = false;
-                }
-            });
-
-            if line_empty {
-                zero_rows.push(y);
-            }
-        });
+        .flat_map(|(y, line)| {
+            line.iter()
+                .enumerate()
+                .filter(|(_, c)| **c == b'#')
+                .map(move |(x, _)| (x, y))
+        })
+        .collect()
+}
 
-    galaxies.sort();
+fn empty_before(coords: impl Iterator<Item = usize> + Clone) -> Vec<usize> {
+    let size = coords.clone().max().map_or(0, |max| max + 1);
+    let mut occupied = vec![false; size];
+    for coord in coords.clone() {
+        occupied[coord] = true;
+    }
 
-    let num_cols = input.iter().take_while(|c| **c != b'\n').count();
-    for x in 0..=num_cols - 1 {
-        if !occupied_cols.contains(&x) {
-            zero_cols.push(x);
+    let mut result = vec![0; size];
+    let mut empty = 0;
+    for (coord, is_occupied) in occupied.iter().enumerate() {
+        result[coord] = empty;
+        if !is_occupied {
+            empty += 1;
         }
     }
 
-    let mut result = 0;
+    coords.map(|coord| result[coord]).collect()
+}
 
-    for (i, (x1, y1)) in galaxies.iter().enumerate() {
-        for (x2, y2) in &galaxies[i + 1..] {
-            let x1_: usize = *(x1.min(x2));
-            let x2_: usize = *(x1.max(x2));
+fn sum_pairwise_distances(mut coords: Vec<usize>) -> usize {
+    coords.sort_unstable();
+    coords
+        .iter()
+        .enumerate()
+        .fold((0, 0), |(result, prefix), (i, coord)| {
+            (result + i * coord - prefix, prefix + coord)
+        })
+        .0
+}
 
-            let y1_: usize = *(y1.min(y2));
-            let y2_: usize = *(y1.max(y2));
+struct GalaxyImage {
+    galaxies: Vec<(usize, usize)>,
+    empty: Vec<(usize, usize)>,
+}
 
-            let mut distance = x1_.abs_diff(x2_) + y1_.abs_diff(y2_);
+impl GalaxyImage {
+    fn parse(input: &[u8]) -> Self {
+        let galaxies = parse_galaxies(input);
+        let empty = std::iter::zip(
+            empty_before(galaxies.iter().map(|(x, _)| *x)),
+            empty_before(galaxies.iter().map(|(_, y)| *y)),
+        )
+        .collect();
 
-            for x in zero_cols.iter() {
-                if *x > x1_ && *x < x2_ {
-                    distance += scale - 1;
-                }
-            }
+        Self { galaxies, empty }
+    }
 
-            for y in zero_rows.iter() {
-                if *y > y1_ && *y < y2_ {
-                    distance += scale - 1;
-                }
-            }
+    fn expanded(&self, scale: usize) -> Vec<(usize, usize)> {
+        std::iter::zip(self.galaxies.iter(), self.empty.iter())
+            .map(|((x, y), (empty_x, empty_y))| {
+                (x + (scale - 1) * empty_x, y + (scale - 1) * empty_y)
+            })
+            .collect()
+    }
 
-            result += distance;
-        }
+    fn distance(&self, a: usize, b: usize, scale: usize) -> Option<usize> {
+        let (x1, y1) = self.galaxies.get(a.checked_sub(1)?)?;
+        let (x2, y2) = self.galaxies.get(b.checked_sub(1)?)?;
+        let (empty_x1, empty_y1) = self.empty[a - 1];
+        let (empty_x2, empty_y2) = self.empty[b - 1];
+
+        Some(
+            x1.abs_diff(*x2)
+                + y1.abs_diff(*y2)
+                + (scale - 1) * (empty_x1.abs_diff(empty_x2) + empty_y1.abs_diff(empty_y2)),
+        )
+    }
+
+    fn nearest_and_farthest(&self, scale: usize) -> Vec<Option<(usize, usize)>> {
+        let expanded = self.expanded(scale);
+
+        expanded
+            .iter()
+            .enumerate()
+            .map(|(i, (x1, y1))| {
+                let distances = expanded
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != i)
+                    .map(|(j, (x2, y2))| (x1.abs_diff(*x2) + y1.abs_diff(*y2), j + 1));
+                let nearest = distances.clone().min()?.1;
+                let farthest = distances
+                    .min_by_key(|(distance, j)| (usize::MAX - distance, *j))?
+                    .1;
+                Some((nearest, farthest))
+            })
+            .collect()
+    }
+
+    fn totals(&self, scales: &[usize]) -> Vec<usize> {
+        let base = sum_pairwise_distances(self.galaxies.iter().map(|(x, _)| *x).collect())
+            + sum_pairwise_distances(self.galaxies.iter().map(|(_, y)| *y).collect());
+        let empty = sum_pairwise_distances(self.empty.iter().map(|(x, _)| *x).collect())
+            + sum_pairwise_distances(self.empty.iter().map(|(_, y)| *y).collect());
+
+        scales
+            .iter()
+            .map(|scale| base + (scale - 1) * empty)
+            .collect()
     }
+}
 
-    result
+fn solution(input: &[u8], scale: usize) -> usize {
+    GalaxyImage::parse(input).totals(&[scale])[0]
 }
 
 fn part1(input: &[u8]) -> usize {
@@ -84,9 +136,33 @@ pub fn main() {
     println!("{}", part2(input));
 }
 
+pub fn explain() {
+    let image = GalaxyImage::parse(INPUT.trim_ascii_end());
+    let expanded = image.expanded(2);
+
+    for (i, pair) in image.nearest_and_farthest(2).into_iter().enumerate() {
+        let (x, y) = expanded[i];
+        match pair {
+            Some((nearest, farthest)) => println!(
+                "Galaxy {} at {},{}: nearest {} ({}), farthest {} ({})",
+                i + 1,
+                x,
+                y,
+                nearest,
+                image.distance(i + 1, nearest, 2).unwrap(),
+                farthest,
+                image.distance(i + 1, farthest, 2).unwrap()
+            ),
+            None => println!("Galaxy {} at {},{}", i + 1, x, y),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::XorShift;
+    use std::collections::HashSet;
     use test::Bencher;
 
     const TEST_INPUT: &[u8] = include_bytes!("../test_inputs/day11.txt");
@@ -109,6 +185,153 @@ mod tests {
         assert_eq!(solution(input, 100), 8410);
     }
 
+    #[test]
+    fn test_galaxy_image() {
+        let input = TEST_INPUT.trim_ascii_end();
+        let image = GalaxyImage::parse(input);
+        assert_eq!(image.totals(&[2, 10, 100]), vec![374, 1030, 8410]);
+
+        assert_eq!(image.distance(5, 9, 2), Some(9));
+        assert_eq!(image.distance(1, 7, 2), Some(15));
+        assert_eq!(image.distance(3, 6, 2), Some(17));
+        assert_eq!(image.distance(8, 9, 2), Some(5));
+        assert_eq!(image.distance(8, 9, 10), Some(13));
+        assert_eq!(image.distance(1, 1, 10), Some(0));
+        assert_eq!(image.distance(0, 1, 2), None);
+        assert_eq!(image.distance(1, 10, 2), None);
+
+        assert_eq!(
+            image.expanded(2),
+            vec![
+                (4, 0),
+                (9, 1),
+                (0, 2),
+                (8, 5),
+                (1, 6),
+                (12, 7),
+                (9, 10),
+                (0, 11),
+                (5, 11)
+            ]
+        );
+
+        let nearest_and_farthest = image.nearest_and_farthest(2);
+        assert_eq!(nearest_and_farthest[4], Some((3, 2)));
+        assert_eq!(nearest_and_farthest[7], Some((9, 2)));
+        assert_eq!(nearest_and_farthest[8], Some((7, 2)));
+
+        let image = GalaxyImage::parse(b"..#..");
+        assert_eq!(image.nearest_and_farthest(2), vec![None]);
+        assert_eq!(image.totals(&[1, 2]), vec![0, 0]);
+    }
+
+    fn solution_reference(input: &[u8], scale: usize) -> usize {
+        let mut galaxies: Vec<(usize, usize)> = vec![];
+        let mut zero_rows: Vec<usize> = vec![];
+        let mut zero_cols: Vec<usize> = vec![];
+        let mut occupied_cols: HashSet<usize> = HashSet::new();
+
+        input
+            .split(|c| *c == b'\n')
+            .enumerate()
+            .for_each(|(y, line)| {
+                let mut line_empty = true;
+
+                line.iter().enumerate().for_each(|(x, c)| {
+                    if *c == b'#' {
+                        galaxies.push((x, y));
+                        occupied_cols.insert(x);
+                        line_empty = false;
+                    }
+                });
+
+                if line_empty {
+                    zero_rows.push(y);
+                }
+            });
+
+        galaxies.sort();
+
+        let num_cols = input.iter().take_while(|c| **c != b'\n').count();
+        for x in 0..=num_cols - 1 {
+            if !occupied_cols.contains(&x) {
+                zero_cols.push(x);
+            }
+        }
+
+        let mut result = 0;
+
+        for (i, (x1, y1)) in galaxies.iter().enumerate() {
+            for (x2, y2) in &galaxies[i + 1..] {
+                let x1_: usize = *(x1.min(x2));
+                let x2_: usize = *(x1.max(x2));
+
+                let y1_: usize = *(y1.min(y2));
+                let y2_: usize = *(y1.max(y2));
+
+                let mut distance = x1_.abs_diff(x2_) + y1_.abs_diff(y2_);
+
+                for x in zero_cols.iter() {
+                    if *x > x1_ && *x < x2_ {
+                        distance += scale - 1;
+                    }
+                }
+
+                for y in zero_rows.iter() {
+                    if *y > y1_ && *y < y2_ {
+                        distance += scale - 1;
+                    }
+                }
+
+                result += distance;
+            }
+        }
+
+        result
+    }
+
+    fn generate_image(rng: &mut XorShift, rows: usize, cols: usize, density: usize) -> Vec<u8> {
+        (0..rows)
+            .map(|_| {
+                (0..cols)
+                    .map(|_| if rng.next(density) == 0 { b'#' } else { b'.' })
+                    .collect::<Vec<u8>>()
+            })
+            .collect::<Vec<Vec<u8>>>()
+            .join(&b'\n')
+    }
+
+    #[test]
+    fn test_solution_reference() {
+        let mut rng = XorShift(0x2023_1211);
+        for _ in 0..200 {
+            let (rows, cols) = (1 + rng.next(20), 1 + rng.next(20));
+            let density = 2 + rng.next(10);
+            let input = generate_image(&mut rng, rows, cols, density);
+            for scale in [1, 2, 10, 1000000] {
+                assert_eq!(solution(&input, scale), solution_reference(&input, scale));
+            }
+        }
+    }
+
+    #[test]
+    fn test_solution_many_galaxies() {
+        // Guards the prefix-sum totals on an image too large for the pairwise reference:
+        // 40000 galaxies on the even columns of 200 rows have a closed-form total.
+        let (rows, cols) = (200, 200);
+        let line = vec![&b"#"[..]; cols].join(&b'.');
+        let input = vec![line; rows].join(&b'\n');
+        assert_eq!(parse_galaxies(&input).len(), rows * cols);
+
+        let pairs_1d = |n: usize| n * (n * n - 1) / 6;
+        for scale in [1, 2, 1000000] {
+            assert_eq!(
+                solution(&input, scale),
+                rows * rows * (scale + 1) * pairs_1d(cols) + cols * cols * pairs_1d(rows)
+            );
+        }
+    }
+
     #[bench]
     fn bench_part1(b: &mut Bencher) {
         let input = INPUT.trim_ascii_end();
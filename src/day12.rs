@@ -1,147 +1,362 @@
 extern crate test;
 
-use itertools::Itertools;
+use std::hash::{BuildHasher, RandomState};
+use std::io::Read;
 
 const INPUT: &[u8] = include_bytes!("../inputs/day12.txt");
 
-fn springs_matches_counts(springs: &[u8], counts: &[usize]) -> bool {
-    let mut actual_counts = springs
-        .split(|c| *c == b'.')
-        .filter(|group| !group.is_empty())
-        .map(|group| group.len());
+#[derive(Debug, PartialEq, Eq)]
+enum SpringError {
+    Overflow,
+    NoArrangement,
+}
+
+struct Row {
+    springs: Vec<u8>,
+    counts: Vec<usize>,
+}
+
+impl Row {
+    fn parse(line: &[u8]) -> Self {
+        let mut groups = line.split(|c| *c == b' ');
+        let springs = groups.next().unwrap().to_vec();
+        let counts = groups
+            .next()
+            .unwrap()
+            .split(|c| *c == b',')
+            .map(|num| num.iter().fold(0, |acc, c| acc * 10 + (c - b'0') as usize))
+            .collect();
+
+        Self { springs, counts }
+    }
+
+    fn unfold(&self, factor: usize) -> Self {
+        Self {
+            springs: vec![self.springs.clone(); factor].join(&b'?'),
+            counts: self.counts.repeat(factor),
+        }
+    }
 
-    let mut counts = counts.iter();
+    fn arrangements(&self) -> Result<u128, SpringError> {
+        Ok(ArrangementTable::new(self)?.count())
+    }
 
-    loop {
-        match (actual_counts.next(), counts.next()) {
-            (None, None) => break,
-            (Some(a), Some(b)) => {
-                if a != *b {
-                    return false;
+    fn deduce(&self) -> Result<Vec<u8>, SpringError> {
+        let table = ArrangementTable::new(self)?;
+        let count = table.count();
+        if count == 0 {
+            return Err(SpringError::NoArrangement);
+        }
+
+        Ok(table
+            .damaged_counts()
+            .iter()
+            .map(|damaged| match *damaged {
+                0 => b'.',
+                damaged if damaged == count => b'#',
+                _ => b'?',
+            })
+            .collect())
+    }
+}
+
+struct ArrangementTable<'a> {
+    row: &'a Row,
+    width: usize,
+    damaged_run: Vec<usize>,
+    ways: Vec<u128>,
+}
+
+impl<'a> ArrangementTable<'a> {
+    fn new(row: &'a Row) -> Result<Self, SpringError> {
+        let len = row.springs.len();
+        let width = row.counts.len() + 1;
+
+        let mut damaged_run = vec![0; len + 1];
+        for i in (0..len).rev() {
+            if row.springs[i] != b'.' {
+                damaged_run[i] = damaged_run[i + 1] + 1;
+            }
+        }
+
+        let mut table = Self {
+            row,
+            width,
+            damaged_run,
+            ways: vec![0; (len + 1) * width],
+        };
+        let mut reachable = vec![false; (len + 1) * width];
+        reachable[0] = true;
+        for i in 0..len {
+            for j in 0..width {
+                if reachable[i * width + j] {
+                    if let Some(next) = table.operational(i) {
+                        reachable[next * width + j] = true;
+                    }
+                    if let Some(next) = table.damaged(i, j) {
+                        reachable[next * width + j + 1] = true;
+                    }
                 }
             }
-            _ => return false,
         }
+
+        table.ways[len * width + width - 1] = 1;
+
+        for i in (0..len).rev() {
+            for j in (0..width).filter(|j| reachable[i * width + j]) {
+                let operational = table.operational(i).map_or(0, |next| table.ways(next, j));
+                let damaged = table
+                    .damaged(i, j)
+                    .map_or(0, |next| table.ways(next, j + 1));
+                table.ways[i * width + j] = operational
+                    .checked_add(damaged)
+                    .ok_or(SpringError::Overflow)?;
+            }
+        }
+
+        Ok(table)
     }
 
-    true
-}
+    fn count(&self) -> u128 {
+        self.ways[0]
+    }
 
-fn part1(input: &[u8]) -> usize {
-    input
-        .split(|c| *c == b'\n')
-        .map(|line| {
-            let mut groups = line.split(|c| *c == b' ');
-            let springs = groups.next().unwrap();
-            let counts: Vec<usize> = groups
-                .next()
-                .unwrap()
-                .split(|c| *c == b',')
-                .map(|num| num.iter().fold(0, |acc, c| acc * 10 + (c - b'0') as usize))
-                .collect();
+    fn ways(&self, i: usize, j: usize) -> u128 {
+        self.ways[i * self.width + j]
+    }
 
-            let pos_unknown: Vec<usize> = springs
-                .iter()
-                .enumerate()
-                .filter(|(_, c)| **c == b'?')
-                .map(|(i, _)| i)
-                .collect();
+    fn operational(&self, i: usize) -> Option<usize> {
+        (self.row.springs[i] != b'#').then_some(i + 1)
+    }
 
-            let total_damaged: usize = counts.iter().sum();
+    fn damaged(&self, i: usize, j: usize) -> Option<usize> {
+        let len = self.row.springs.len();
+        let group = *self.row.counts.get(j)?;
+        let end = i + group;
 
-            let known_damaged: usize = springs.iter().filter(|c| **c == b'#').count();
+        (self.damaged_run[i] >= group && (end == len || self.row.springs[end] != b'#'))
+            .then_some((end + 1).min(len))
+    }
 
-            let mut springs_buf = vec![0_u8; springs.len()];
+    fn fill(springs: &mut [u8], i: usize, next: usize, group: usize) {
+        springs[i..i + group].fill(b'#');
+        springs[i + group..next].fill(b'.');
+    }
 
-            pos_unknown
-                .iter()
-                .combinations(total_damaged - known_damaged)
-                .filter(|combination| {
-                    for (i, &c) in springs.iter().enumerate() {
-                        match c {
-                            b'.' => springs_buf[i] = c,
-                            b'#' => springs_buf[i] = c,
-                            b'?' => {
-                                if combination.contains(&&i) {
-                                    springs_buf[i] = b'#'
-                                } else {
-                                    springs_buf[i] = b'.'
-                                }
-                            }
-                            _ => panic!(),
-                        }
-                    }
+    fn iter(&self) -> impl Iterator<Item = Vec<u8>> + '_ {
+        let len = self.row.springs.len();
+        let mut stack = vec![];
+        if self.count() > 0 {
+            stack.push((0, 0, self.row.springs.clone()));
+        }
 
-                    springs_matches_counts(&springs_buf, &counts)
-                })
-                .count()
+        std::iter::from_fn(move || {
+            while let Some((i, j, mut springs)) = stack.pop() {
+                if i == len {
+                    return Some(springs);
+                }
+
+                if let Some(next) = self
+                    .damaged(i, j)
+                    .filter(|next| self.ways(*next, j + 1) > 0)
+                {
+                    let mut springs = springs.clone();
+                    Self::fill(&mut springs, i, next, self.row.counts[j]);
+                    stack.push((next, j + 1, springs));
+                }
+
+                if let Some(next) = self.operational(i).filter(|next| self.ways(*next, j) > 0) {
+                    springs[i] = b'.';
+                    stack.push((next, j, springs));
+                }
+            }
+
+            None
         })
-        .sum()
-}
+    }
 
-fn part2(input: &[u8]) -> usize {
-    input
-        .split(|c| *c == b'\n')
-        .map(|line| {
-            let mut groups = line.split(|c| *c == b' ');
-            let springs_single = groups.next().unwrap();
-            let mut springs = vec![];
-            for _ in 0..=4 {
-                springs.extend_from_slice(springs_single);
-                springs.push(b'?');
+    fn sample(&self, mut rng: impl FnMut(u128) -> u128) -> Option<Vec<u8>> {
+        let len = self.row.springs.len();
+        let mut springs = self.row.springs.clone();
+        let (mut i, mut j) = (0, 0);
+
+        if self.count() == 0 {
+            return None;
+        }
+
+        while i < len {
+            let operational = self.operational(i).map_or(0, |next| self.ways(next, j));
+            if rng(self.ways(i, j)) < operational {
+                springs[i] = b'.';
+                i += 1;
+            } else {
+                let next = self.damaged(i, j).unwrap();
+                Self::fill(&mut springs, i, next, self.row.counts[j]);
+                (i, j) = (next, j + 1);
             }
-            springs.pop();
+        }
 
-            let counts_single: Vec<usize> = groups
-                .next()
-                .unwrap()
-                .split(|c| *c == b',')
-                .map(|num| num.iter().fold(0, |acc, c| acc * 10 + (c - b'0') as usize))
-                .collect();
-            let mut counts = vec![];
-            for _ in 0..=4 {
-                counts.extend_from_slice(&counts_single);
+        Some(springs)
+    }
+
+    fn damaged_counts(&self) -> Vec<u128> {
+        let len = self.row.springs.len();
+        let width = self.width;
+
+        let mut reached = vec![0_u128; (len + 1) * width];
+        reached[0] = 1;
+        let mut starts = vec![0_u128; len + 1];
+        let mut ends = vec![0_u128; len + 1];
+
+        for i in 0..len {
+            for j in 0..width {
+                let count = reached[i * width + j];
+                if count == 0 {
+                    continue;
+                }
+
+                if let Some(next) = self.operational(i).filter(|next| self.ways(*next, j) > 0) {
+                    reached[next * width + j] += count;
+                }
+
+                if let Some(next) = self
+                    .damaged(i, j)
+                    .filter(|next| self.ways(*next, j + 1) > 0)
+                {
+                    reached[next * width + j + 1] += count;
+
+                    let mass = count * self.ways(next, j + 1);
+                    starts[i] += mass;
+                    ends[i + self.row.counts[j]] += mass;
+                }
             }
+        }
 
-            let mut memo = vec![None; 1024 * 1024];
-            recurse(&springs, &counts, &mut memo)
-        })
-        .sum()
+        std::iter::zip(starts, ends)
+            .take(len)
+            .scan(0, |damaged, (start, end)| {
+                *damaged = *damaged - end + start;
+                Some(*damaged)
+            })
+            .collect()
+    }
+
+    fn damaged_probabilities(&self) -> Option<Vec<f64>> {
+        let count = self.count();
+        if count == 0 {
+            return None;
+        }
+
+        Some(
+            self.damaged_counts()
+                .iter()
+                .map(|damaged| *damaged as f64 / count as f64)
+                .collect(),
+        )
+    }
+}
+
+struct Nonogram {
+    row_counts: Vec<Vec<usize>>,
+    col_counts: Vec<Vec<usize>>,
 }
 
-fn recurse(springs: &[u8], groups: &[usize], memo: &mut [Option<usize>]) -> usize {
-    if let Some(count) = memo[1024 * springs.len() + groups.len()] {
-        count
-    } else {
-        let mut count = 0;
-        let remaining: usize = groups.iter().sum();
-        let margin = springs.len() - remaining;
-        let group = groups[0];
+impl Nonogram {
+    fn new(row_counts: Vec<Vec<usize>>, col_counts: Vec<Vec<usize>>) -> Self {
+        Self {
+            row_counts,
+            col_counts,
+        }
+    }
+
+    fn parse(input: &[u8]) -> Self {
+        let parse_counts = |block: &[u8]| -> Vec<Vec<usize>> {
+            block
+                .split(|c| *c == b'\n')
+                .map(|line| {
+                    line.split(|c| *c == b',')
+                        .map(|num| num.iter().fold(0, |acc, c| acc * 10 + (c - b'0') as usize))
+                        .filter(|count| *count > 0)
+                        .collect()
+                })
+                .collect()
+        };
+
+        let mut blocks = input.split(|c| *c == b'\n').collect::<Vec<_>>();
+        let separator = blocks.iter().position(|line| line.is_empty()).unwrap();
+        let col_block = blocks.split_off(separator + 1);
+        blocks.pop();
+
+        Self::new(
+            parse_counts(&blocks.join(&b'\n')),
+            parse_counts(&col_block.join(&b'\n')),
+        )
+    }
+
+    fn solve(&self) -> Result<Vec<Vec<u8>>, SpringError> {
+        let (height, width) = (self.row_counts.len(), self.col_counts.len());
+        let mut grid = vec![vec![b'?'; width]; height];
+        let mut dirty_rows = vec![true; height];
+        let mut dirty_cols = vec![true; width];
+
+        while dirty_rows.contains(&true) || dirty_cols.contains(&true) {
+            for y in 0..height {
+                if !std::mem::take(&mut dirty_rows[y]) {
+                    continue;
+                }
 
-        for i in 0..=margin {
-            if i > 0 && springs[i - 1] == b'#' {
-                break;
+                let row = Row {
+                    springs: grid[y].clone(),
+                    counts: self.row_counts[y].clone(),
+                };
+                for (x, cell) in row.deduce()?.into_iter().enumerate() {
+                    if grid[y][x] != cell {
+                        grid[y][x] = cell;
+                        dirty_cols[x] = true;
+                    }
+                }
             }
 
-            if springs[i..i + group].iter().all(|c| *c != b'.') {
-                if groups.len() == 1 {
-                    if springs[i + group..].iter().all(|c| *c != b'#') {
-                        count += 1;
+            for x in 0..width {
+                if !std::mem::take(&mut dirty_cols[x]) {
+                    continue;
+                }
+
+                let col = Row {
+                    springs: grid.iter().map(|row| row[x]).collect(),
+                    counts: self.col_counts[x].clone(),
+                };
+                for (y, cell) in col.deduce()?.into_iter().enumerate() {
+                    if grid[y][x] != cell {
+                        grid[y][x] = cell;
+                        dirty_rows[y] = true;
                     }
-                } else if i + group == springs.len()
-                    || springs[i + group] != b'#' && springs.len() > i + remaining
-                {
-                    count += recurse(&springs[i + group + 1..], &groups[1..], memo);
                 }
             }
         }
 
-        memo[1024 * springs.len() + groups.len()] = Some(count);
-
-        count
+        Ok(grid)
     }
 }
 
+fn solution(input: &[u8], factor: usize) -> Result<u128, SpringError> {
+    input
+        .split(|c| *c == b'\n')
+        .try_fold(0_u128, |total, line| {
+            total
+                .checked_add(Row::parse(line).unfold(factor).arrangements()?)
+                .ok_or(SpringError::Overflow)
+        })
+}
+
+fn part1(input: &[u8]) -> u128 {
+    solution(input, 1).unwrap()
+}
+
+fn part2(input: &[u8]) -> u128 {
+    solution(input, 5).unwrap()
+}
+
 pub fn main() {
     let input = INPUT.trim_ascii_end();
 
@@ -149,9 +364,81 @@ pub fn main() {
     println!("{}", part2(input));
 }
 
+pub fn explain() {
+    for line in INPUT.trim_ascii_end().split(|c| *c == b'\n') {
+        let row = Row::parse(line);
+        let table = ArrangementTable::new(&row).unwrap();
+
+        println!("{}", String::from_utf8_lossy(line));
+        match table.damaged_probabilities() {
+            Some(probabilities) => println!(
+                "  damaged: {}",
+                probabilities
+                    .iter()
+                    .map(|p| format!("{:.2}", p))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+            None => println!("  no arrangement"),
+        }
+        for springs in table.iter() {
+            println!("  {}", String::from_utf8_lossy(&springs));
+        }
+    }
+}
+
+pub fn deduce() {
+    for line in INPUT.trim_ascii_end().split(|c| *c == b'\n') {
+        match Row::parse(line).deduce() {
+            Ok(springs) => println!(
+                "{} -> {}",
+                String::from_utf8_lossy(line),
+                String::from_utf8_lossy(&springs)
+            ),
+            Err(err) => println!("{} -> {:?}", String::from_utf8_lossy(line), err),
+        }
+    }
+}
+
+pub fn nonogram() {
+    let mut input = vec![];
+    std::io::stdin().read_to_end(&mut input).unwrap();
+
+    match Nonogram::parse(input.trim_ascii_end()).solve() {
+        Ok(grid) => {
+            for row in grid {
+                println!("{}", String::from_utf8_lossy(&row));
+            }
+        }
+        Err(err) => println!("{:?}", err),
+    }
+}
+
+pub fn sample() {
+    let state = RandomState::new();
+    let mut counter = 0_u64;
+    let mut rng = |bound: u128| {
+        let high = state.hash_one(counter) as u128;
+        let low = state.hash_one(counter + 1) as u128;
+        counter += 2;
+        (high << 64 | low) % bound
+    };
+
+    for line in INPUT.trim_ascii_end().split(|c| *c == b'\n') {
+        let row = Row::parse(line);
+        let table = ArrangementTable::new(&row).unwrap();
+
+        match table.sample(&mut rng) {
+            Some(springs) => println!("{}", String::from_utf8_lossy(&springs)),
+            None => println!("no arrangement"),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::XorShift;
     use test::Bencher;
 
     const TEST_INPUT: &[u8] = include_bytes!("../test_inputs/day12.txt");
@@ -168,6 +455,329 @@ mod tests {
         assert_eq!(part2(input), 525152);
     }
 
+    #[test]
+    fn test_unfold() {
+        let row = Row::parse(b".# 1").unfold(3);
+        assert_eq!(row.springs, b".#?.#?.#");
+        assert_eq!(row.counts, vec![1, 1, 1]);
+
+        let input = TEST_INPUT.trim_ascii_end();
+        let arrangements: Vec<u128> = input
+            .split(|c| *c == b'\n')
+            .map(|line| Row::parse(line).unfold(5).arrangements().unwrap())
+            .collect();
+        assert_eq!(arrangements, vec![1, 16384, 1, 16, 2500, 506250]);
+        assert_eq!(solution(input, 0), Ok(6));
+
+        for line in input.split(|c| *c == b'\n') {
+            let row = Row::parse(line).unfold(2);
+            assert_eq!(row.arrangements(), Ok(arrangements_reference(&row)));
+        }
+    }
+
+    fn springs_matches_counts(springs: &[u8], counts: &[usize]) -> bool {
+        springs
+            .split(|c| *c == b'.')
+            .filter(|group| !group.is_empty())
+            .map(|group| group.len())
+            .eq(counts.iter().copied())
+    }
+
+    fn arrangements_reference(row: &Row) -> u128 {
+        let unknown: Vec<usize> = (0..row.springs.len())
+            .filter(|i| row.springs[*i] == b'?')
+            .collect();
+
+        (0..1_usize << unknown.len())
+            .filter(|mask| {
+                let mut springs = row.springs.clone();
+                for (bit, i) in unknown.iter().enumerate() {
+                    springs[*i] = if mask & (1 << bit) != 0 { b'#' } else { b'.' };
+                }
+                springs_matches_counts(&springs, &row.counts)
+            })
+            .count() as u128
+    }
+
+    fn generate_row(rng: &mut XorShift, len: usize) -> Row {
+        let springs: Vec<u8> = (0..len).map(|_| b".#?"[rng.next(3)]).collect();
+        let counts = (0..rng.next(4)).map(|_| 1 + rng.next(4)).collect();
+        Row { springs, counts }
+    }
+
+    #[test]
+    fn test_arrangements_reference() {
+        let mut rng = XorShift(0x2023_1212);
+        for _ in 0..500 {
+            let len = rng.next(13);
+            let row = generate_row(&mut rng, len);
+            assert_eq!(row.arrangements(), Ok(arrangements_reference(&row)));
+        }
+    }
+
+    #[test]
+    fn test_long_rows() {
+        let row = Row::parse(b"#.# 1,1").unfold(1500);
+        assert!(row.springs.len() > 1023 && row.counts.len() > 1023);
+        assert_eq!(row.arrangements(), Ok(1));
+
+        let row = Row {
+            springs: vec![b'?'; 2047],
+            counts: vec![1; 1024],
+        };
+        assert_eq!(row.arrangements(), Ok(1));
+
+        let row = Row {
+            springs: vec![b'?'; 2048],
+            counts: vec![1; 1024],
+        };
+        assert_eq!(row.arrangements(), Ok(1025));
+
+        let row = Row {
+            springs: vec![b'?'; 130],
+            counts: vec![1; 40],
+        };
+        assert_eq!(row.arrangements(), Ok(106828484056363129130148078));
+
+        let row = Row {
+            springs: vec![b'?'; 200],
+            counts: vec![1; 60],
+        };
+        assert_eq!(row.arrangements(), Err(SpringError::Overflow));
+    }
+
+    #[test]
+    fn test_iter() {
+        let input = TEST_INPUT.trim_ascii_end();
+        for line in input.split(|c| *c == b'\n') {
+            let row = Row::parse(line);
+            let table = ArrangementTable::new(&row).unwrap();
+            let arrangements: Vec<Vec<u8>> = table.iter().collect();
+            assert_eq!(arrangements.len() as u128, table.count());
+            assert!(arrangements.windows(2).all(|pair| pair[0] > pair[1]));
+            for arrangement in arrangements {
+                assert!(springs_matches_counts(&arrangement, &row.counts));
+                assert!(
+                    std::iter::zip(&arrangement, &row.springs).all(|(a, b)| *b == b'?' || a == b)
+                );
+            }
+        }
+
+        let row = Row::parse(b"???.### 1,1,3");
+        let table = ArrangementTable::new(&row).unwrap();
+        assert_eq!(table.iter().next(), Some(b"#.#.###".to_vec()));
+
+        let row = Row::parse(b"##.? 3");
+        assert_eq!(ArrangementTable::new(&row).unwrap().iter().next(), None);
+
+        let row = Row {
+            springs: vec![b'?'; 100],
+            counts: vec![1; 10],
+        };
+        let table = ArrangementTable::new(&row).unwrap();
+        assert_eq!(table.iter().take(3).count(), 3);
+    }
+
+    #[test]
+    fn test_sample() {
+        let mut rng = XorShift(0x2023_1212);
+
+        let row = Row::parse(b"?###???????? 3,2,1");
+        let table = ArrangementTable::new(&row).unwrap();
+        let arrangements: Vec<Vec<u8>> = table.iter().collect();
+        let mut hits = vec![0; arrangements.len()];
+        for _ in 0..10000 {
+            let sample = table.sample(|bound| rng.next_wide(bound)).unwrap();
+            hits[arrangements.iter().position(|a| *a == sample).unwrap()] += 1;
+        }
+        assert!(hits.iter().all(|hits| (800..1200).contains(hits)));
+
+        let row = Row::parse(b"?###???????? 3,2,1").unfold(5);
+        let table = ArrangementTable::new(&row).unwrap();
+        for _ in 0..100 {
+            let sample = table.sample(|bound| rng.next_wide(bound)).unwrap();
+            assert!(springs_matches_counts(&sample, &row.counts));
+        }
+
+        let row = Row::parse(b"#.# 2");
+        assert_eq!(ArrangementTable::new(&row).unwrap().sample(|_| 0), None);
+    }
+
+    #[test]
+    fn test_wide_rows() {
+        let mut rng = XorShift(0x2023_1212);
+        let row = Row {
+            springs: vec![b'?'; 130],
+            counts: vec![1; 40],
+        };
+        let table = ArrangementTable::new(&row).unwrap();
+        assert!(table.count() > u64::MAX as u128);
+
+        let probabilities = table.damaged_probabilities().unwrap();
+        assert!(probabilities.iter().all(|p| (0.0..=1.0).contains(p)));
+        assert!((probabilities[0] - 40.0 / 91.0).abs() < 1e-12);
+        assert!((probabilities.iter().sum::<f64>() - 40.0).abs() < 1e-9);
+
+        let mut first_damaged = 0;
+        for _ in 0..2000 {
+            let sample = table.sample(|bound| rng.next_wide(bound)).unwrap();
+            assert!(springs_matches_counts(&sample, &row.counts));
+            if sample[0] == b'#' {
+                first_damaged += 1;
+            }
+        }
+        assert!((800..960).contains(&first_damaged));
+    }
+
+    #[test]
+    fn test_damaged_probabilities() {
+        let row = Row::parse(b"???.### 1,1,3");
+        assert_eq!(
+            ArrangementTable::new(&row).unwrap().damaged_probabilities(),
+            Some(vec![1.0, 0.0, 1.0, 0.0, 1.0, 1.0, 1.0])
+        );
+
+        let row = Row::parse(b".??..??...?##. 1,1,3");
+        assert_eq!(
+            ArrangementTable::new(&row).unwrap().damaged_probabilities(),
+            Some(vec![
+                0.0, 0.5, 0.5, 0.0, 0.0, 0.5, 0.5, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 0.0
+            ])
+        );
+
+        let mut rng = XorShift(0x2023_1212);
+        for _ in 0..200 {
+            let len = rng.next(13);
+            let row = generate_row(&mut rng, len);
+            let table = ArrangementTable::new(&row).unwrap();
+            let mut damaged = vec![0; len];
+            for arrangement in table.iter() {
+                for (i, spring) in arrangement.iter().enumerate() {
+                    if *spring == b'#' {
+                        damaged[i] += 1;
+                    }
+                }
+            }
+            assert_eq!(table.damaged_counts(), damaged);
+        }
+
+        assert_eq!(
+            ArrangementTable::new(&Row::parse(b"#.# 2"))
+                .unwrap()
+                .damaged_probabilities(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_deduce() {
+        assert_eq!(
+            Row::parse(b"?###???????? 3,2,1").deduce(),
+            Ok(b".###.???????".to_vec())
+        );
+        assert_eq!(
+            Row::parse(b"?????????? 8").deduce(),
+            Ok(b"??######??".to_vec())
+        );
+        assert_eq!(
+            Row::parse(b"#.# 2").deduce(),
+            Err(SpringError::NoArrangement)
+        );
+
+        let mut rng = XorShift(0x2023_1212);
+        for _ in 0..200 {
+            let len = rng.next(13);
+            let row = generate_row(&mut rng, len);
+            let arrangements: Vec<Vec<u8>> = ArrangementTable::new(&row).unwrap().iter().collect();
+            let expected = if arrangements.is_empty() {
+                Err(SpringError::NoArrangement)
+            } else {
+                Ok((0..len)
+                    .map(|i| {
+                        if arrangements.iter().all(|a| a[i] == b'#') {
+                            b'#'
+                        } else if arrangements.iter().all(|a| a[i] == b'.') {
+                            b'.'
+                        } else {
+                            b'?'
+                        }
+                    })
+                    .collect())
+            };
+            assert_eq!(row.deduce(), expected);
+        }
+
+        let mut springs = vec![b'?'; 130];
+        springs[0] = b'#';
+        springs[129] = b'#';
+        let row = Row {
+            springs,
+            counts: vec![1; 40],
+        };
+        assert!(ArrangementTable::new(&row).unwrap().count() > u64::MAX as u128);
+        let deduced = row.deduce().unwrap();
+        assert_eq!(&deduced[..2], b"#.");
+        assert_eq!(&deduced[128..], b".#");
+        assert!(deduced[2..128].iter().all(|cell| *cell == b'?'));
+
+        let row = Row {
+            springs: vec![b'?'; 200],
+            counts: vec![1; 60],
+        };
+        assert_eq!(row.deduce(), Err(SpringError::Overflow));
+    }
+
+    fn counts_of(line: &[u8]) -> Vec<usize> {
+        line.split(|c| *c == b'.')
+            .filter(|group| !group.is_empty())
+            .map(|group| group.len())
+            .collect()
+    }
+
+    fn nonogram_of(grid: &[Vec<u8>]) -> Nonogram {
+        let width = grid.first().map_or(0, |row| row.len());
+        Nonogram::new(
+            grid.iter().map(|row| counts_of(row)).collect(),
+            (0..width)
+                .map(|x| counts_of(&grid.iter().map(|row| row[x]).collect::<Vec<u8>>()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_nonogram() {
+        let grid: Vec<Vec<u8>> = ["#....", "##.#.", "###..", "####.", "#####"]
+            .iter()
+            .map(|row| row.as_bytes().to_vec())
+            .collect();
+        assert_eq!(nonogram_of(&grid).solve(), Ok(grid));
+
+        let nonogram = Nonogram::new(vec![vec![1], vec![1]], vec![vec![1], vec![1]]);
+        assert_eq!(nonogram.solve(), Ok(vec![b"??".to_vec(), b"??".to_vec()]));
+
+        let nonogram = Nonogram::new(vec![vec![2], vec![]], vec![vec![], vec![]]);
+        assert_eq!(nonogram.solve(), Err(SpringError::NoArrangement));
+
+        let nonogram = Nonogram::parse(b"1\n1,1\n0\n\n2\n0\n1");
+        assert_eq!(nonogram.row_counts, vec![vec![1], vec![1, 1], vec![]]);
+        assert_eq!(nonogram.col_counts, vec![vec![2], vec![], vec![1]]);
+        assert_eq!(
+            nonogram.solve(),
+            Ok(vec![b"#..".to_vec(), b"#.#".to_vec(), b"...".to_vec()])
+        );
+
+        let mut rng = XorShift(0x2023_1212);
+        for _ in 0..100 {
+            let (height, width) = (1 + rng.next(8), 1 + rng.next(8));
+            let grid: Vec<Vec<u8>> = (0..height)
+                .map(|_| (0..width).map(|_| b".#"[rng.next(2)]).collect())
+                .collect();
+            let solved = nonogram_of(&grid).solve().unwrap();
+            assert!(std::iter::zip(grid.concat(), solved.concat())
+                .all(|(cell, solved)| solved == b'?' || solved == cell));
+        }
+    }
+
     #[bench]
     fn bench_part1(b: &mut Bencher) {
         let input = INPUT.trim_ascii_end();
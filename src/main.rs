@@ -29,8 +29,33 @@ mod day23;
 mod day24;
 mod day25;
 
+#[cfg(test)]
+mod test_utils;
+
 fn main() {
     if let Some(day) = env::args().nth(1) {
+        if let Some(mode) = env::args().nth(2) {
+            match (day.as_str(), mode.as_str()) {
+                ("03", "explain") => day03::explain(),
+                ("04", "explain") => day04::explain(),
+                ("05", "explain") => day05::explain(),
+                ("07", "explain") => day07::explain(),
+                ("09", "explain") => day09::explain(),
+                ("09", "strict") => day09::strict(),
+                ("10", "explain") => day10::explain(),
+                ("10", "scan" | "shoelace") => day10::with_area_method(&mode),
+                ("11", "explain") => day11::explain(),
+                ("12", "explain") => day12::explain(),
+                ("12", "deduce") => day12::deduce(),
+                ("12", "nonogram") => day12::nonogram(),
+                ("12", "sample") => day12::sample(),
+                _ => {
+                    panic!("invalid argument for \"mode\": {}", mode)
+                }
+            }
+            return;
+        }
+
         match day.as_str() {
             "01" => day01::main(),
             "02" => day02::main(),
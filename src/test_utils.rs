@@ -0,0 +1,18 @@
+pub struct XorShift(pub u64);
+
+impl XorShift {
+    pub fn step(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    pub fn next(&mut self, bound: usize) -> usize {
+        (self.step() % bound as u64) as usize
+    }
+
+    pub fn next_wide(&mut self, bound: u128) -> u128 {
+        ((self.step() as u128) << 64 | self.step() as u128) % bound
+    }
+}